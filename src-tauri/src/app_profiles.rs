@@ -0,0 +1,194 @@
+// Per-application profiles: the app that had focus when recording started
+// picks the mode, language, STT engine and insertion method.
+//
+// Frontmost-app detection is platform specific: NSWorkspace on macOS,
+// the EWMH `_NET_ACTIVE_WINDOW` property via xprop on Linux/X11, and
+// GetForegroundWindow on Windows. Wayland sessions have no equivalent, so
+// detection returns None there and the global settings apply.
+//
+// On Linux this needs the `xprop` utility (x11-utils on Debian/Ubuntu,
+// xorg-xprop on Arch and Fedora). Without it profiles never match, and a
+// line saying so is logged once.
+
+use serde::{Deserialize, Serialize};
+
+/// The application that had focus when recording started
+#[derive(Debug, Clone, Serialize)]
+pub struct FrontmostApp {
+    /// Bundle id on macOS ("com.tinyspeck.slackmacgap"), WM_CLASS on Linux
+    /// ("code"), executable name on Windows ("slack.exe")
+    pub id: String,
+    /// Human-readable name, e.g. "Slack"
+    pub name: String,
+}
+
+/// Settings applied while dictating into specific apps. Unset fields fall
+/// back to the global settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppProfile {
+    pub id: String,
+    pub name: String,
+    /// App ids or names this profile applies to, compared case-insensitively
+    pub apps: Vec<String>,
+    pub enabled: bool,
+    #[serde(default)]
+    pub mode_id: Option<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    /// STT provider store value ("groq", "parakeet", ...)
+    #[serde(default)]
+    pub stt_provider: Option<String>,
+    /// "paste" or "type"
+    #[serde(default)]
+    pub insertion_method: Option<String>,
+}
+
+impl AppProfile {
+    fn matches(&self, app: &FrontmostApp) -> bool {
+        self.apps.iter().map(|a| a.trim()).any(|a| {
+            !a.is_empty() && (a.eq_ignore_ascii_case(&app.id) || a.eq_ignore_ascii_case(&app.name))
+        })
+    }
+}
+
+/// First enabled profile that lists the app
+pub fn find_profile<'a>(profiles: &'a [AppProfile], app: &FrontmostApp) -> Option<&'a AppProfile> {
+    profiles
+        .iter()
+        .filter(|p| p.enabled)
+        .find(|p| p.matches(app))
+}
+
+#[cfg(target_os = "macos")]
+pub fn detect_frontmost_app() -> Option<FrontmostApp> {
+    use objc2_app_kit::NSWorkspace;
+
+    objc2::rc::autoreleasepool(|_| {
+        let app = NSWorkspace::sharedWorkspace().frontmostApplication()?;
+        let id = app.bundleIdentifier().map(|s| s.to_string());
+        let name = app.localizedName().map(|s| s.to_string());
+
+        match (id, name) {
+            (None, None) => None,
+            (id, name) => Some(FrontmostApp {
+                id: id.clone().or(name.clone()).unwrap_or_default(),
+                name: name.or(id).unwrap_or_default(),
+            }),
+        }
+    })
+}
+
+#[cfg(target_os = "linux")]
+pub fn detect_frontmost_app() -> Option<FrontmostApp> {
+    use std::process::Command;
+    use std::sync::Once;
+
+    static MISSING_XPROP: Once = Once::new();
+
+    fn xprop(args: &[&str]) -> Option<String> {
+        let output = match Command::new("xprop").args(args).output() {
+            Ok(output) => output,
+            Err(e) => {
+                if e.kind() == std::io::ErrorKind::NotFound {
+                    MISSING_XPROP.call_once(|| {
+                        println!(
+                            "[AppProfiles] xprop not found; install it (x11-utils) to use app profiles"
+                        );
+                    });
+                } else {
+                    println!("[AppProfiles] Failed to run xprop: {}", e);
+                }
+                return None;
+            }
+        };
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    // "_NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007"
+    let active = xprop(&["-root", "_NET_ACTIVE_WINDOW"])?;
+    let window_id = active
+        .split_whitespace()
+        .last()?
+        .trim_end_matches(',')
+        .to_string();
+    if window_id == "0x0" {
+        return None;
+    }
+
+    // "WM_CLASS(STRING) = "code", "Code""
+    let class = xprop(&["-id", &window_id, "WM_CLASS"])?;
+    let parts: Vec<&str> = class.split('"').skip(1).step_by(2).collect();
+    let instance = parts.first()?.to_string();
+    let class_name = parts
+        .get(1)
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| instance.clone());
+
+    Some(FrontmostApp {
+        id: instance,
+        name: class_name,
+    })
+}
+
+#[cfg(target_os = "windows")]
+pub fn detect_frontmost_app() -> Option<FrontmostApp> {
+    use std::ffi::c_void;
+    use std::os::windows::ffi::OsStringExt;
+    use std::path::PathBuf;
+
+    type Handle = *mut c_void;
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetForegroundWindow() -> Handle;
+        fn GetWindowThreadProcessId(hwnd: Handle, process_id: *mut u32) -> u32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn OpenProcess(access: u32, inherit: i32, process_id: u32) -> Handle;
+        fn QueryFullProcessImageNameW(
+            process: Handle,
+            flags: u32,
+            name: *mut u16,
+            size: *mut u32,
+        ) -> i32;
+        fn CloseHandle(handle: Handle) -> i32;
+    }
+
+    let path = unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.is_null() {
+            return None;
+        }
+        let mut pid = 0u32;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if process.is_null() {
+            return None;
+        }
+        let mut buffer = [0u16; 1024];
+        let mut len = buffer.len() as u32;
+        let ok = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut len);
+        CloseHandle(process);
+        if ok == 0 {
+            return None;
+        }
+        PathBuf::from(std::ffi::OsString::from_wide(&buffer[..len as usize]))
+    };
+
+    Some(FrontmostApp {
+        id: path.file_name()?.to_string_lossy().to_lowercase(),
+        name: path.file_stem()?.to_string_lossy().into_owned(),
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn detect_frontmost_app() -> Option<FrontmostApp> {
+    None
+}
@@ -1,3 +1,4 @@
+use crate::vad::{VadConfig, VoiceActivityDetector};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{Device, SampleFormat, Stream, StreamConfig};
 use rubato::{FftFixedIn, Resampler};
@@ -6,8 +7,9 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
-const TARGET_SAMPLE_RATE: u32 = 24000;
+pub const TARGET_SAMPLE_RATE: u32 = 24000;
 const CHUNK_DURATION_MS: u64 = 100;
 const MAX_BUFFER_SAMPLES: usize = 24000 * 60; // 1 minute of audio at 24kHz
 
@@ -22,11 +24,21 @@ pub struct AudioDevice {
     pub is_default: bool,
 }
 
+/// Hands-free stop: fires `on_silence` once after speech was heard and then
+/// `silence_timeout` of continuous silence followed
+pub struct AutoStop {
+    pub silence_timeout: Duration,
+    pub vad: VadConfig,
+    pub on_silence: Sender<()>,
+}
+
 enum AudioCommand {
     Start {
         device_id: Option<String>,
         audio_sender: Sender<Vec<u8>>,
         level_sender: Sender<f32>,
+        warning_sender: Sender<String>,
+        auto_stop: Option<AutoStop>,
     },
     Stop,
     Shutdown,
@@ -57,17 +69,23 @@ impl AudioCaptureHandle {
         }
     }
 
+    /// `warning_sender` gets user-facing messages about problems with the
+    /// capture that don't stop it, such as audio being dropped
     pub fn start(
         &self,
         device_id: Option<String>,
         audio_sender: Sender<Vec<u8>>,
         level_sender: Sender<f32>,
+        warning_sender: Sender<String>,
+        auto_stop: Option<AutoStop>,
     ) -> Result<(), String> {
         self.command_tx
             .send(AudioCommand::Start {
                 device_id,
                 audio_sender,
                 level_sender,
+                warning_sender,
+                auto_stop,
             })
             .map_err(|e| format!("Failed to send start command: {}", e))
     }
@@ -162,11 +180,19 @@ fn audio_thread(command_rx: Receiver<AudioCommand>) {
                 device_id,
                 audio_sender,
                 level_sender,
+                warning_sender,
+                auto_stop,
             }) => {
                 // Stop any existing stream and wait for cleanup
                 stop_active_stream(&mut current_stream);
 
-                match create_stream(device_id.as_deref(), audio_sender, level_sender) {
+                match create_stream(
+                    device_id.as_deref(),
+                    audio_sender,
+                    level_sender,
+                    warning_sender,
+                    auto_stop,
+                ) {
                     Ok(active_stream) => {
                         current_stream = Some(active_stream);
                         println!("[Audio] Capture started");
@@ -195,6 +221,7 @@ fn process_samples_to_buffer(
     buffer: &Arc<Mutex<Vec<f32>>>,
     level_sender: &Sender<f32>,
     samples_counter: &AtomicUsize,
+    dropped_counter: &AtomicUsize,
 ) {
     // Convert to mono if stereo
     let mono_samples: Vec<f32> = if channels > 1 {
@@ -220,9 +247,15 @@ fn process_samples_to_buffer(
     match buffer.lock() {
         Ok(mut buf) => {
             let available_space = MAX_BUFFER_SAMPLES.saturating_sub(buf.len());
-            if available_space > 0 {
-                let samples_to_add = mono_samples.len().min(available_space);
-                buf.extend(&mono_samples[..samples_to_add]);
+            let samples_to_add = mono_samples.len().min(available_space);
+            buf.extend(&mono_samples[..samples_to_add]);
+
+            // Only happens if the processing thread stalls for a full minute
+            let dropped = mono_samples.len() - samples_to_add;
+            if dropped > 0 && dropped_counter.fetch_add(dropped, Ordering::Relaxed) == 0 {
+                eprintln!(
+                    "[Audio] Capture buffer full, processing thread is stalled; dropping samples"
+                );
             }
         }
         Err(poisoned) => {
@@ -237,6 +270,56 @@ fn process_samples_to_buffer(
     }
 }
 
+/// Tracks trailing silence on the resampled stream for [`AutoStop`]
+struct SilenceWatch {
+    detector: VoiceActivityDetector,
+    config: AutoStop,
+    heard_speech: bool,
+    silent_samples: usize,
+    timeout_samples: usize,
+    fired: bool,
+}
+
+impl SilenceWatch {
+    fn new(config: AutoStop) -> Self {
+        let timeout_samples =
+            (config.silence_timeout.as_secs_f64() * TARGET_SAMPLE_RATE as f64) as usize;
+        Self {
+            detector: VoiceActivityDetector::new(TARGET_SAMPLE_RATE, config.vad.clone()),
+            config,
+            heard_speech: false,
+            silent_samples: 0,
+            timeout_samples,
+            fired: false,
+        }
+    }
+
+    fn feed(&mut self, samples: &[f32]) {
+        if self.fired {
+            return;
+        }
+
+        let frame_len = self.detector.frame_len();
+        for is_speech in self.detector.process(samples) {
+            if is_speech {
+                self.heard_speech = true;
+                self.silent_samples = 0;
+            } else if self.heard_speech {
+                self.silent_samples += frame_len;
+            }
+        }
+
+        if self.heard_speech && self.silent_samples >= self.timeout_samples {
+            println!(
+                "[Audio] {:.1}s of silence after speech, requesting auto-stop",
+                self.config.silence_timeout.as_secs_f32()
+            );
+            self.fired = true;
+            let _ = self.config.on_silence.send(());
+        }
+    }
+}
+
 /// Convert float sample to PCM16
 fn float_to_pcm16(sample: f32) -> i16 {
     let clamped = sample.clamp(-1.0, 1.0);
@@ -251,6 +334,8 @@ fn create_stream(
     device_id: Option<&str>,
     audio_sender: Sender<Vec<u8>>,
     level_sender: Sender<f32>,
+    warning_sender: Sender<String>,
+    auto_stop: Option<AutoStop>,
 ) -> Result<ActiveStream, String> {
     let device = get_device_by_id(device_id)?;
     let config = device
@@ -289,6 +374,7 @@ fn create_stream(
     let is_capturing = Arc::new(AtomicBool::new(true));
 
     let samples_received = Arc::new(AtomicUsize::new(0));
+    let samples_dropped = Arc::new(AtomicUsize::new(0));
 
     let err_fn = |err| eprintln!("[Audio] Stream error: {}", err);
 
@@ -299,6 +385,7 @@ fn create_stream(
             let is_capturing_clone = is_capturing.clone();
             let level_sender_clone = level_sender.clone();
             let samples_received_clone = samples_received.clone();
+            let samples_dropped_clone = samples_dropped.clone();
 
             device.build_input_stream(
                 &stream_config,
@@ -312,6 +399,7 @@ fn create_stream(
                         &audio_buffer_clone,
                         &level_sender_clone,
                         &samples_received_clone,
+                        &samples_dropped_clone,
                     );
                 },
                 err_fn,
@@ -323,6 +411,7 @@ fn create_stream(
             let is_capturing_clone = is_capturing.clone();
             let level_sender_clone = level_sender.clone();
             let samples_received_clone = samples_received.clone();
+            let samples_dropped_clone = samples_dropped.clone();
 
             device.build_input_stream(
                 &stream_config,
@@ -341,6 +430,7 @@ fn create_stream(
                         &audio_buffer_clone,
                         &level_sender_clone,
                         &samples_received_clone,
+                        &samples_dropped_clone,
                     );
                 },
                 err_fn,
@@ -360,10 +450,13 @@ fn create_stream(
     let resampler_process = resampler.clone();
     let is_capturing_process = is_capturing.clone();
     let samples_received_log = samples_received.clone();
+    let samples_dropped_log = samples_dropped.clone();
 
     let processing_thread = thread::spawn(move || {
         let mut total_bytes_sent: usize = 0;
         let mut iteration_count: usize = 0;
+        let mut silence_watch = auto_stop.map(SilenceWatch::new);
+        let mut warned_about_drops = false;
 
         loop {
             thread::sleep(std::time::Duration::from_millis(CHUNK_DURATION_MS));
@@ -382,6 +475,16 @@ fn create_stream(
             iteration_count += 1;
             let is_running = is_capturing_process.load(Ordering::SeqCst);
 
+            // The capture callback can't block, so it drops audio when this
+            // thread falls behind; the recording then has a gap
+            if !warned_about_drops && samples_dropped_log.load(Ordering::Relaxed) > 0 {
+                warned_about_drops = true;
+                let _ = warning_sender.send(
+                    "Audio was dropped because the system fell behind - the transcript may be missing words"
+                        .to_string(),
+                );
+            }
+
             // Process samples if we have any
             if !samples.is_empty() {
                 // Resample if needed
@@ -426,6 +529,10 @@ fn create_stream(
                     samples
                 };
 
+                if let Some(ref mut watch) = silence_watch {
+                    watch.feed(&resampled);
+                }
+
                 // Convert to PCM16
                 let pcm16: Vec<u8> = resampled
                     .iter()
@@ -441,9 +548,10 @@ fn create_stream(
             // Exit after processing remaining data if stopped
             if !is_running {
                 let total_samples = samples_received_log.load(Ordering::Relaxed);
+                let dropped_samples = samples_dropped_log.load(Ordering::Relaxed);
                 println!(
-                    "[Audio] Processing thread finished: {} iterations, {} samples received, {} bytes sent",
-                    iteration_count, total_samples, total_bytes_sent
+                    "[Audio] Processing thread finished: {} iterations, {} samples received, {} dropped, {} bytes sent",
+                    iteration_count, total_samples, dropped_samples, total_bytes_sent
                 );
                 break;
             }
@@ -0,0 +1,407 @@
+// Chunked transcription of long files. Audio is decoded a window at a time
+// and cut in pauses near the target length; where speech runs on, windows
+// overlap instead. Per-window transcripts are stitched back together: words
+// and segments by their timestamps, text by aligning the overlapping words.
+
+use crate::decode::{self, WindowReader};
+use crate::stt::TimedTranscript;
+use crate::subtitles::{Segment, Word};
+use crate::wav::{self, WavWriter};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Window length for local inference. Bounds memory (~19MB of samples at
+/// 16kHz) and keeps progress updates coming on multi-hour files.
+pub const LOCAL_WINDOW_SECS: f64 = 300.0;
+/// Audio shared by consecutive windows that couldn't be cut in a pause, so
+/// a word cut by one window's edge is heard whole by the other
+pub const WINDOW_OVERLAP_SECS: f64 = 5.0;
+/// How far back from the target length to look for a pause to cut at
+pub const PAUSE_SEARCH_SECS: f64 = 30.0;
+
+/// Fewer matching words than this is treated as a coincidence
+const MIN_ALIGN_MATCH: usize = 2;
+/// Words at the very edge of a window that may be transcribed differently on
+/// each side (cut off mid-word), and can be skipped when aligning
+const EDGE_SLACK_WORDS: usize = 1;
+/// Upper bound on speaking rate, for sizing the overlap when the engine
+/// returned no word timings
+const MAX_WORDS_PER_SEC: f64 = 4.0;
+
+/// Called with (window index, total windows) before each window is transcribed
+pub type WindowProgress = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Estimated number of windows for a file of `duration` seconds. Cutting in
+/// pauses makes windows a little shorter, so this can come out low.
+pub fn window_count(duration: f64, window_secs: f64) -> usize {
+    if duration <= window_secs {
+        return 1;
+    }
+    let step = window_secs - PAUSE_SEARCH_SECS / 2.0;
+    1 + ((duration - window_secs) / step).ceil() as usize
+}
+
+/// Transcribe a file window by window. `infer` gets 16kHz mono samples and
+/// returns timestamps relative to the window.
+pub fn transcribe_windowed(
+    path: &Path,
+    progress: Option<&WindowProgress>,
+    mut infer: impl FnMut(&[f32]) -> Result<TimedTranscript, String>,
+) -> Result<TimedTranscript, String> {
+    let total = decode::probe_duration(path)
+        .map(|duration| window_count(duration, LOCAL_WINDOW_SECS))
+        .unwrap_or(1);
+    let mut reader = WindowReader::open(
+        path,
+        decode::SPEECH_SAMPLE_RATE,
+        LOCAL_WINDOW_SECS,
+        WINDOW_OVERLAP_SECS,
+        PAUSE_SEARCH_SECS,
+    )?;
+
+    let mut stitcher = Stitcher::default();
+    let mut index = 0;
+    while let Some(window) = reader.next_window()? {
+        if let Some(progress) = progress {
+            progress(index, total.max(index + 1));
+        }
+        if total > 1 {
+            println!(
+                "[Chunking] Window {} at {:.0}s ({:.0}s, {:.1}s overlap)",
+                index + 1,
+                window.start_secs,
+                window.samples.len() as f64 / decode::SPEECH_SAMPLE_RATE as f64,
+                window.overlap_secs
+            );
+        }
+
+        let transcript = infer(&window.samples)?;
+        stitcher.push(transcript, window.start_secs, window.overlap_secs);
+        index += 1;
+    }
+
+    Ok(stitcher.finish())
+}
+
+/// A piece of a long file written out for upload
+pub struct AudioChunk {
+    pub path: PathBuf,
+    pub start_secs: f64,
+    pub overlap_secs: f64,
+}
+
+/// Split a file into 16kHz mono WAV chunks of at most `chunk_secs`, cut in
+/// pauses where possible. Replaces fixed-length stream copies, which cut
+/// words mid-syllable.
+pub fn split_into_wav_chunks(
+    input_path: &Path,
+    output_dir: &Path,
+    chunk_secs: f64,
+) -> Result<Vec<AudioChunk>, String> {
+    let sample_rate = decode::SPEECH_SAMPLE_RATE;
+    let mut reader = WindowReader::open(
+        input_path,
+        sample_rate,
+        chunk_secs,
+        WINDOW_OVERLAP_SECS,
+        PAUSE_SEARCH_SECS,
+    )?;
+
+    let mut chunks = Vec::new();
+    while let Some(window) = reader.next_window()? {
+        let path = output_dir.join(format!("chunk_{:03}.wav", chunks.len()));
+        let file =
+            File::create(&path).map_err(|e| format!("Failed to create chunk file: {}", e))?;
+        let mut writer = WavWriter::create(file, sample_rate)?;
+        writer.write_pcm16(&wav::f32_to_pcm16(&window.samples))?;
+        writer.finish()?;
+
+        chunks.push(AudioChunk {
+            path,
+            start_secs: window.start_secs,
+            overlap_secs: window.overlap_secs,
+        });
+    }
+
+    println!(
+        "[Chunking] Split {:?} into {} chunks",
+        input_path,
+        chunks.len()
+    );
+    Ok(chunks)
+}
+
+/// Joins per-window transcripts into one
+#[derive(Default)]
+pub struct Stitcher {
+    merged: Option<TimedTranscript>,
+}
+
+impl Stitcher {
+    /// Add the transcript of a window starting at `start_secs` in the file.
+    /// Its timestamps are relative to the window.
+    pub fn push(&mut self, mut transcript: TimedTranscript, start_secs: f64, overlap_secs: f64) {
+        transcript.offset(start_secs);
+        self.merged = Some(match self.merged.take() {
+            None => transcript,
+            Some(previous) => stitch(previous, transcript, start_secs, overlap_secs),
+        });
+    }
+
+    pub fn finish(self) -> TimedTranscript {
+        self.merged.unwrap_or_default()
+    }
+}
+
+fn midpoint(start: f64, end: f64) -> f64 {
+    (start + end) / 2.0
+}
+
+/// Join the transcript of a window onto everything before it. Timed items
+/// centered before the middle of the overlap come from `previous`, the rest
+/// from `next`; without an overlap that is simply the window start.
+fn stitch(
+    previous: TimedTranscript,
+    next: TimedTranscript,
+    start_secs: f64,
+    overlap_secs: f64,
+) -> TimedTranscript {
+    let boundary = start_secs + overlap_secs / 2.0;
+
+    let text = if overlap_secs > 0.0 {
+        let overlap_end = start_secs + overlap_secs;
+        let previous_overlap =
+            overlap_word_count(&previous.words, overlap_secs, |w| w.end > start_secs);
+        let next_overlap = overlap_word_count(&next.words, overlap_secs, |w| w.start < overlap_end);
+        merge_overlapping_text(&previous.text, &next.text, previous_overlap, next_overlap)
+    } else {
+        join_text(&previous.text, &next.text)
+    };
+
+    let mut words: Vec<Word> = previous
+        .words
+        .into_iter()
+        .filter(|w| midpoint(w.start, w.end) < boundary)
+        .collect();
+    words.extend(
+        next.words
+            .into_iter()
+            .filter(|w| midpoint(w.start, w.end) >= boundary),
+    );
+
+    let mut segments: Vec<Segment> = previous
+        .segments
+        .into_iter()
+        .filter(|s| midpoint(s.start, s.end) < boundary)
+        .collect();
+    segments.extend(
+        next.segments
+            .into_iter()
+            .filter(|s| midpoint(s.start, s.end) >= boundary),
+    );
+
+    TimedTranscript {
+        text,
+        segments,
+        words,
+    }
+}
+
+/// How many words of a transcript were spoken during the overlap, from word
+/// timings when the engine has them and a generous estimate otherwise
+fn overlap_word_count(
+    words: &[Word],
+    overlap_secs: f64,
+    in_overlap: impl Fn(&Word) -> bool,
+) -> usize {
+    if words.is_empty() {
+        (overlap_secs * MAX_WORDS_PER_SEC).ceil() as usize
+    } else {
+        words.iter().filter(|w| in_overlap(w)).count()
+    }
+}
+
+fn join_text(previous: &str, next: &str) -> String {
+    match (previous.trim(), next.trim()) {
+        ("", next) => next.to_string(),
+        (previous, "") => previous.to_string(),
+        (previous, next) => format!("{} {}", previous, next),
+    }
+}
+
+/// Lowercase word without surrounding punctuation, for comparing the two
+/// transcriptions of an overlap
+fn normalize_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// Join two transcripts whose audio overlapped. Only the last
+/// `previous_overlap` words of `previous` and the first `next_overlap` words
+/// of `next` were heard twice, so the two are lined up on the longest run of
+/// words that ends `previous` and starts `next`, give or take a word cut off
+/// at the window edge. `previous` is kept through that run and `next`
+/// continues after it. With no such run the texts are joined as they are;
+/// repeating a few words beats losing any.
+fn merge_overlapping_text(
+    previous: &str,
+    next: &str,
+    previous_overlap: usize,
+    next_overlap: usize,
+) -> String {
+    let prev_words: Vec<&str> = previous.split_whitespace().collect();
+    let next_words: Vec<&str> = next.split_whitespace().collect();
+    let prev_norm: Vec<String> = prev_words.iter().map(|w| normalize_word(w)).collect();
+    let next_norm: Vec<String> = next_words.iter().map(|w| normalize_word(w)).collect();
+    let tail = previous_overlap.min(prev_words.len());
+    let head = next_overlap.min(next_words.len());
+
+    for length in (MIN_ALIGN_MATCH..=tail.min(head)).rev() {
+        for prev_skip in 0..=EDGE_SLACK_WORDS {
+            for next_skip in 0..=EDGE_SLACK_WORDS {
+                if length + prev_skip > tail || length + next_skip > head {
+                    continue;
+                }
+                let prev_end = prev_words.len() - prev_skip;
+                let prev_run = &prev_norm[prev_end - length..prev_end];
+                let next_run = &next_norm[next_skip..next_skip + length];
+                if prev_run.iter().all(|w| !w.is_empty()) && prev_run == next_run {
+                    println!("[Chunking] Aligned overlap on {} words", length);
+                    let kept = prev_words[..prev_end].join(" ");
+                    let rest = next_words[next_skip + length..].join(" ");
+                    return join_text(&kept, &rest);
+                }
+            }
+        }
+    }
+
+    join_text(previous, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(start: f64, end: f64, text: &str) -> Word {
+        Word {
+            start,
+            end,
+            text: text.to_string(),
+            probability: None,
+        }
+    }
+
+    fn transcript(text: &str, words: Vec<Word>) -> TimedTranscript {
+        TimedTranscript {
+            text: text.to_string(),
+            segments: Vec::new(),
+            words,
+        }
+    }
+
+    #[test]
+    fn merge_drops_words_heard_twice() {
+        let merged = merge_overlapping_text("we went to the market", "The market was closed", 2, 2);
+        assert_eq!(merged, "we went to the market was closed");
+    }
+
+    #[test]
+    fn merge_skips_a_word_cut_at_the_window_edge() {
+        let merged = merge_overlapping_text("we decided to go hom", "to go home and rest", 4, 4);
+        assert_eq!(merged, "we decided to go home and rest");
+    }
+
+    #[test]
+    fn merge_ignores_repeated_phrases_outside_the_overlap() {
+        let previous = "we talked for an hour and in the end the board agreed to move forward with the new plan";
+        let next = "people hear today and in the end we decided to stay";
+        let merged = merge_overlapping_text(previous, next, 40, 40);
+        assert_eq!(merged, format!("{} {}", previous, next));
+    }
+
+    #[test]
+    fn merge_only_looks_inside_the_overlap() {
+        // "the plan" ends `previous` and starts `next`, but only one word of
+        // each side was spoken in the overlap
+        let merged = merge_overlapping_text("this is the plan", "the plan is good", 1, 1);
+        assert_eq!(merged, "this is the plan the plan is good");
+    }
+
+    #[test]
+    fn merge_without_a_match_keeps_every_word() {
+        let merged = merge_overlapping_text("first part", "second part", 5, 5);
+        assert_eq!(merged, "first part second part");
+    }
+
+    #[test]
+    fn stitcher_joins_windows_cut_in_a_pause() {
+        let mut stitcher = Stitcher::default();
+        stitcher.push(
+            transcript(
+                "Hello there.",
+                vec![word(0.0, 0.5, "Hello"), word(0.6, 1.0, "there.")],
+            ),
+            0.0,
+            0.0,
+        );
+        stitcher.push(
+            transcript(
+                "How are you?",
+                vec![word(0.25, 0.5, "How"), word(0.75, 1.25, "are you?")],
+            ),
+            2.0,
+            0.0,
+        );
+        let result = stitcher.finish();
+
+        assert_eq!(result.text, "Hello there. How are you?");
+        let starts: Vec<f64> = result.words.iter().map(|w| w.start).collect();
+        assert_eq!(starts, vec![0.0, 0.6, 2.25, 2.75]);
+    }
+
+    #[test]
+    fn stitcher_de_duplicates_overlapping_windows() {
+        let mut stitcher = Stitcher::default();
+        stitcher.push(
+            transcript(
+                "One two three four five.",
+                vec![
+                    word(0.0, 0.8, "One"),
+                    word(1.0, 1.8, "two"),
+                    word(2.0, 2.6, "three"),
+                    word(2.7, 3.3, "four"),
+                    word(3.4, 3.9, "five."),
+                ],
+            ),
+            0.0,
+            0.0,
+        );
+        // Window starts at 2.5s and repeats the audio up to 4.0s
+        stitcher.push(
+            transcript(
+                "four five six",
+                vec![
+                    word(0.2, 0.8, "four"),
+                    word(0.9, 1.4, "five"),
+                    word(1.6, 2.2, "six"),
+                ],
+            ),
+            2.5,
+            1.5,
+        );
+        let result = stitcher.finish();
+
+        assert_eq!(result.text, "One two three four five. six");
+        let texts: Vec<&str> = result.words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(texts, vec!["One", "two", "three", "four", "five", "six"]);
+        assert!((result.words[5].start - 4.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stitcher_without_windows_is_empty() {
+        let result = Stitcher::default().finish();
+        assert!(result.text.is_empty());
+        assert!(result.words.is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+use tauri::image::Image;
+use tauri::AppHandle;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+/// What was on the clipboard before auto-paste overwrote it. The clipboard
+/// plugin can't read HTML, so rich text comes back as its plain-text flavor.
+pub enum ClipboardSnapshot {
+    Text(String),
+    Image(Image<'static>),
+    Empty,
+}
+
+impl ClipboardSnapshot {
+    pub fn capture(app: &AppHandle) -> Self {
+        let clipboard = app.clipboard();
+        if let Ok(text) = clipboard.read_text() {
+            if !text.is_empty() {
+                return Self::Text(text);
+            }
+        }
+        if let Ok(image) = clipboard.read_image() {
+            return Self::Image(image.to_owned());
+        }
+        Self::Empty
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Text(_) => "text",
+            Self::Image(_) => "image",
+            Self::Empty => "empty",
+        }
+    }
+
+    /// Put the snapshot back, unless the clipboard no longer holds `pasted`
+    /// (the user copied something else meanwhile, which must win)
+    pub fn restore(self, app: &AppHandle, pasted: &str) -> Result<(), String> {
+        let clipboard = app.clipboard();
+        if clipboard.read_text().ok().as_deref() != Some(pasted) {
+            println!("[Clipboard] Clipboard changed since paste, not restoring");
+            return Ok(());
+        }
+
+        let kind = self.kind();
+        match self {
+            Self::Text(text) => clipboard.write_text(text),
+            Self::Image(image) => clipboard.write_image(&image),
+            Self::Empty => clipboard.clear(),
+        }
+        .map_err(|e| format!("Failed to restore clipboard: {}", e))?;
+
+        println!("[Clipboard] Restored previous {} contents", kind);
+        Ok(())
+    }
+}
@@ -0,0 +1,613 @@
+// In-process audio decoding for local engines, so transcribing files doesn't
+// need ffmpeg. Symphonia handles mp3, m4a/mp4/mov (AAC, ALAC), ogg (Vorbis),
+// flac, wav and mkv/webm audio; anything it can't read (Opus, AVI, ...) is
+// handed to ffmpeg when it is installed.
+//
+// Audio is decoded as a stream so long files can be transcribed in windows,
+// split at pauses, without holding the whole recording in memory.
+
+use crate::vad::{self, VadConfig};
+use crate::wav::{self, WavWriter};
+use rubato::{FftFixedIn, Resampler};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, ChildStdout, Command, Stdio};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Rate the speech models work at; also used for extracted uploads since
+/// anything higher is thrown away server-side
+pub const SPEECH_SAMPLE_RATE: u32 = 16000;
+
+/// Input frames per resampler call
+const RESAMPLE_CHUNK_SIZE: usize = 1024;
+
+/// Bytes read from ffmpeg's stdout at a time (16k f32 samples)
+const FFMPEG_READ_SIZE: usize = 64 * 1024;
+
+/// An opened file with the first audio track the decoder supports
+struct AudioTrack {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    sample_rate: u32,
+}
+
+fn open_track(path: &Path) -> Result<AudioTrack, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open audio file: {}", e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("Unsupported container: {}", e))?;
+    let format = probed.format;
+
+    // Video containers list the video track first; it has no registered decoder
+    let (track_id, decoder, sample_rate) = format
+        .tracks()
+        .iter()
+        .filter(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .find_map(|t| {
+            let decoder = symphonia::default::get_codecs()
+                .make(&t.codec_params, &DecoderOptions::default())
+                .ok()?;
+            Some((t.id, decoder, t.codec_params.sample_rate?))
+        })
+        .ok_or("No supported audio track found")?;
+
+    Ok(AudioTrack {
+        format,
+        decoder,
+        track_id,
+        sample_rate,
+    })
+}
+
+fn is_end_of_stream(error: &SymphoniaError) -> bool {
+    matches!(error, SymphoniaError::IoError(e) if e.kind() == std::io::ErrorKind::UnexpectedEof)
+}
+
+/// FFT-based resampling with proper anti-aliasing that can be fed in pieces.
+/// Input is processed in fixed-size chunks so the internal resampler state
+/// carries over and no tail audio is lost.
+struct StreamResampler {
+    /// `None` when the rates already match
+    resampler: Option<FftFixedIn<f32>>,
+    pending: Vec<f32>,
+}
+
+impl StreamResampler {
+    fn new(from_rate: u32, to_rate: u32) -> Result<Self, String> {
+        let resampler = if from_rate == to_rate {
+            None
+        } else {
+            Some(
+                FftFixedIn::<f32>::new(
+                    from_rate as usize,
+                    to_rate as usize,
+                    RESAMPLE_CHUNK_SIZE,
+                    1, // sub_chunks
+                    1, // channels
+                )
+                .map_err(|e| format!("Failed to create resampler: {}", e))?,
+            )
+        };
+        Ok(Self {
+            resampler,
+            pending: Vec::new(),
+        })
+    }
+
+    fn push(&mut self, samples: &[f32]) -> Result<Vec<f32>, String> {
+        let Some(resampler) = self.resampler.as_mut() else {
+            return Ok(samples.to_vec());
+        };
+        self.pending.extend_from_slice(samples);
+
+        let mut output = Vec::new();
+        let mut pos = 0;
+        loop {
+            let frames_needed = resampler.input_frames_next();
+            if self.pending.len() - pos < frames_needed {
+                break;
+            }
+            let resampled = resampler
+                .process(&[&self.pending[pos..pos + frames_needed]], None)
+                .map_err(|e| format!("Resampling failed: {}", e))?;
+            if let Some(channel) = resampled.into_iter().next() {
+                output.extend(channel);
+            }
+            pos += frames_needed;
+        }
+        self.pending.drain(..pos);
+        Ok(output)
+    }
+
+    /// Pad out the last partial chunk and flush the resampler's delay
+    fn finish(&mut self) -> Result<Vec<f32>, String> {
+        let Some(resampler) = self.resampler.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        let mut output = Vec::new();
+        if !self.pending.is_empty() {
+            let mut tail = std::mem::take(&mut self.pending);
+            tail.resize(resampler.input_frames_next(), 0.0);
+            let resampled = resampler
+                .process(&[&tail], None)
+                .map_err(|e| format!("Resampling failed: {}", e))?;
+            if let Some(channel) = resampled.into_iter().next() {
+                output.extend(channel);
+            }
+        }
+
+        // Flush remaining samples
+        let flush = vec![0.0f32; resampler.input_frames_next()];
+        if let Ok(resampled) = resampler.process(&[&flush], None) {
+            if let Some(channel) = resampled.into_iter().next() {
+                output.extend(channel);
+            }
+        }
+        Ok(output)
+    }
+}
+
+enum Backend {
+    Native {
+        track: AudioTrack,
+        buffer: Option<SampleBuffer<f32>>,
+        resampler: StreamResampler,
+    },
+    Ffmpeg {
+        child: Child,
+        stdout: ChildStdout,
+        /// Bytes of a sample split across reads
+        leftover: Vec<u8>,
+    },
+}
+
+impl Drop for Backend {
+    /// A stream dropped before the end of the file would otherwise leave
+    /// ffmpeg blocked on a full pipe, and a zombie once it exits
+    fn drop(&mut self) {
+        if let Backend::Ffmpeg { child, .. } = self {
+            child.kill().ok();
+            child.wait().ok();
+        }
+    }
+}
+
+/// Mono audio from a file at a fixed sample rate, produced a packet at a time
+pub struct SampleStream {
+    backend: Backend,
+    finished: bool,
+}
+
+impl SampleStream {
+    /// Open any supported audio or video file. Falls back to ffmpeg for
+    /// formats symphonia can't read.
+    pub fn open(path: &Path, sample_rate: u32) -> Result<Self, String> {
+        let backend = match open_track(path) {
+            Ok(track) => Backend::Native {
+                resampler: StreamResampler::new(track.sample_rate, sample_rate)?,
+                track,
+                buffer: None,
+            },
+            Err(native_error) => {
+                println!(
+                    "[Decode] Native decoding failed ({}), trying ffmpeg",
+                    native_error
+                );
+                spawn_ffmpeg(path, sample_rate).map_err(|ffmpeg_error| {
+                    format!(
+                        "Could not decode audio: {} ({})",
+                        native_error, ffmpeg_error
+                    )
+                })?
+            }
+        };
+        Ok(Self {
+            backend,
+            finished: false,
+        })
+    }
+
+    /// The next run of samples; `None` once the file is exhausted
+    pub fn next_samples(&mut self) -> Result<Option<Vec<f32>>, String> {
+        if self.finished {
+            return Ok(None);
+        }
+        let samples = match &mut self.backend {
+            Backend::Native {
+                track,
+                buffer,
+                resampler,
+            } => next_native(track, buffer, resampler)?,
+            Backend::Ffmpeg {
+                child,
+                stdout,
+                leftover,
+            } => next_ffmpeg(child, stdout, leftover)?,
+        };
+        if samples.is_none() {
+            self.finished = true;
+        }
+        Ok(samples)
+    }
+}
+
+fn next_native(
+    track: &mut AudioTrack,
+    sample_buffer: &mut Option<SampleBuffer<f32>>,
+    resampler: &mut StreamResampler,
+) -> Result<Option<Vec<f32>>, String> {
+    loop {
+        let packet = match track.format.next_packet() {
+            Ok(packet) => packet,
+            Err(e) if is_end_of_stream(&e) => {
+                let tail = resampler.finish()?;
+                return Ok((!tail.is_empty()).then_some(tail));
+            }
+            Err(e) => return Err(format!("Failed to read audio: {}", e)),
+        };
+        if packet.track_id() != track.track_id {
+            continue;
+        }
+
+        let decoded = match track.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt frame shouldn't sink the whole file
+            Err(SymphoniaError::DecodeError(e)) => {
+                println!("[Decode] Skipping bad frame: {}", e);
+                continue;
+            }
+            Err(e) => return Err(format!("Failed to decode audio: {}", e)),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let frames = decoded.capacity();
+        // Reuse the buffer unless this packet is larger than any before
+        if sample_buffer
+            .as_ref()
+            .is_none_or(|b| b.capacity() < frames * channels)
+        {
+            *sample_buffer = Some(SampleBuffer::new(frames as u64, spec));
+        }
+        let Some(buffer) = sample_buffer.as_mut() else {
+            continue;
+        };
+        buffer.copy_interleaved_ref(decoded);
+
+        // Downmix to mono by averaging channels
+        let mono: Vec<f32> = buffer
+            .samples()
+            .chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+        let resampled = resampler.push(&mono)?;
+        if !resampled.is_empty() {
+            return Ok(Some(resampled));
+        }
+    }
+}
+
+/// Decode through ffmpeg straight to mono f32 at the target rate
+fn spawn_ffmpeg(path: &Path, sample_rate: u32) -> Result<Backend, String> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(path)
+        .args(["-vn", "-f", "f32le", "-ac", "1", "-ar"])
+        .arg(sample_rate.to_string())
+        .arg("-")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
+    let stdout = child.stdout.take().ok_or("Failed to read ffmpeg output")?;
+    Ok(Backend::Ffmpeg {
+        child,
+        stdout,
+        leftover: Vec::new(),
+    })
+}
+
+fn next_ffmpeg(
+    child: &mut Child,
+    stdout: &mut ChildStdout,
+    leftover: &mut Vec<u8>,
+) -> Result<Option<Vec<f32>>, String> {
+    let mut bytes = std::mem::take(leftover);
+    let start = bytes.len();
+    bytes.resize(start + FFMPEG_READ_SIZE, 0);
+    let read = stdout
+        .read(&mut bytes[start..])
+        .map_err(|e| format!("Failed to read ffmpeg output: {}", e))?;
+    bytes.truncate(start + read);
+
+    if read == 0 {
+        let status = child
+            .wait()
+            .map_err(|e| format!("Failed to wait for ffmpeg: {}", e))?;
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                pipe.read_to_string(&mut stderr).ok();
+            }
+            return Err(format!("ffmpeg decoding failed: {}", stderr.trim()));
+        }
+        return Ok(None);
+    }
+
+    let whole = bytes.len() - bytes.len() % 4;
+    leftover.extend_from_slice(&bytes[whole..]);
+    Ok(Some(
+        bytes[..whole]
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect(),
+    ))
+}
+
+/// Decode a file into a 16-bit mono WAV, for uploading the audio of a video
+/// when ffmpeg isn't available to extract it
+pub fn decode_to_wav(path: &Path, output_path: &Path, sample_rate: u32) -> Result<(), String> {
+    let mut stream = SampleStream::open(path, sample_rate)?;
+    let file =
+        File::create(output_path).map_err(|e| format!("Failed to create audio file: {}", e))?;
+    let mut writer = WavWriter::create(file, sample_rate)?;
+    while let Some(chunk) = stream.next_samples()? {
+        writer.write_pcm16(&wav::f32_to_pcm16(&chunk))?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// A slice of a file handed to an engine
+pub struct AudioWindow {
+    /// Seconds from the start of the file
+    pub start_secs: f64,
+    /// Seconds at the start of this window that the previous one also
+    /// covered; zero when the previous window ended in a pause
+    pub overlap_secs: f64,
+    pub samples: Vec<f32>,
+}
+
+/// Splits a file into windows of at most `window_secs`. Each window ends in
+/// the longest pause within its last `pause_search_secs`, so words aren't cut
+/// in half; when speech never pauses there, the window is cut at full length
+/// and the next one starts `overlap_secs` earlier so boundary words are heard
+/// whole at least once. Only the current window is kept in memory.
+pub struct WindowReader {
+    stream: SampleStream,
+    sample_rate: u32,
+    window_len: usize,
+    overlap_len: usize,
+    pause_search_len: usize,
+    buffer: Vec<f32>,
+    /// Sample index of `buffer[0]` in the file
+    buffer_start: usize,
+    /// Samples at the start of `buffer` already sent in the previous window
+    buffer_overlap: usize,
+    stream_done: bool,
+}
+
+impl WindowReader {
+    pub fn open(
+        path: &Path,
+        sample_rate: u32,
+        window_secs: f64,
+        overlap_secs: f64,
+        pause_search_secs: f64,
+    ) -> Result<Self, String> {
+        let window_len = (window_secs * sample_rate as f64) as usize;
+        let overlap_len = (overlap_secs * sample_rate as f64) as usize;
+        let pause_search_len = (pause_search_secs * sample_rate as f64) as usize;
+        if overlap_len >= window_len || pause_search_len >= window_len {
+            return Err(
+                "Window overlap and pause search must be shorter than the window".to_string(),
+            );
+        }
+        Ok(Self {
+            stream: SampleStream::open(path, sample_rate)?,
+            sample_rate,
+            window_len,
+            overlap_len,
+            pause_search_len,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            buffer_overlap: 0,
+            stream_done: false,
+        })
+    }
+
+    pub fn next_window(&mut self) -> Result<Option<AudioWindow>, String> {
+        while !self.stream_done && self.buffer.len() < self.window_len {
+            match self.stream.next_samples()? {
+                Some(samples) => self.buffer.extend(samples),
+                None => self.stream_done = true,
+            }
+        }
+
+        // A buffer holding only the overlap was already transcribed as the
+        // end of the previous window
+        if self.buffer.len() <= self.buffer_overlap {
+            self.buffer.clear();
+            return Ok(None);
+        }
+
+        let is_last = self.stream_done && self.buffer.len() <= self.window_len;
+        let pause = if is_last {
+            None
+        } else {
+            let search_from =
+                (self.window_len - self.pause_search_len).max(self.buffer_overlap + 1);
+            vad::find_pause(
+                &self.buffer[..self.window_len],
+                self.sample_rate,
+                search_from,
+                &VadConfig::default(),
+            )
+        };
+        let take = match pause {
+            Some(cut) => cut,
+            None => self.buffer.len().min(self.window_len),
+        };
+
+        let window = AudioWindow {
+            start_secs: self.buffer_start as f64 / self.sample_rate as f64,
+            overlap_secs: self.buffer_overlap as f64 / self.sample_rate as f64,
+            samples: self.buffer[..take].to_vec(),
+        };
+
+        if is_last {
+            self.buffer.clear();
+            self.buffer_overlap = 0;
+        } else {
+            // Cutting in a pause needs no overlap
+            let overlap = if pause.is_some() { 0 } else { self.overlap_len };
+            let advance = take - overlap;
+            self.buffer.drain(..advance);
+            self.buffer_start += advance;
+            self.buffer_overlap = overlap;
+        }
+        Ok(Some(window))
+    }
+}
+
+/// Duration in seconds from the container, without decoding. Formats that
+/// don't store a frame count are measured by summing packet durations.
+pub fn probe_duration(path: &Path) -> Result<f64, String> {
+    let mut track = open_track(path)?;
+    let params = track
+        .format
+        .tracks()
+        .iter()
+        .find(|t| t.id == track.track_id)
+        .map(|t| t.codec_params.clone())
+        .ok_or("Audio track disappeared")?;
+
+    if let Some(frames) = params.n_frames {
+        return Ok(frames as f64 / track.sample_rate as f64);
+    }
+
+    let mut total: u64 = 0;
+    loop {
+        match track.format.next_packet() {
+            Ok(packet) if packet.track_id() == track.track_id => total += packet.dur(),
+            Ok(_) => {}
+            Err(e) if is_end_of_stream(&e) => break,
+            Err(e) => return Err(format!("Failed to read audio: {}", e)),
+        }
+    }
+
+    match params.time_base {
+        Some(time_base) => {
+            let time = time_base.calc_time(total);
+            Ok(time.seconds as f64 + time.frac)
+        }
+        None => Ok(total as f64 / track.sample_rate as f64),
+    }
+}
+
+/// Resample a complete mono signal
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>, String> {
+    if from_rate == to_rate || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+    let mut resampler = StreamResampler::new(from_rate, to_rate)?;
+    let mut output = resampler.push(samples)?;
+    output.extend(resampler.finish()?);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_audio::{silence, speech, RATE};
+    use std::path::PathBuf;
+
+    fn write_wav(dir: &Path, samples: &[f32]) -> PathBuf {
+        let path = dir.join("input.wav");
+        let mut writer = WavWriter::create(File::create(&path).unwrap(), RATE).unwrap();
+        writer.write_pcm16(&wav::f32_to_pcm16(samples)).unwrap();
+        writer.finish().unwrap();
+        path
+    }
+
+    fn read_windows(samples: &[f32]) -> Vec<AudioWindow> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_wav(dir.path(), samples);
+        let mut reader = WindowReader::open(&path, RATE, 4.0, 1.0, 2.0).unwrap();
+        let mut windows = Vec::new();
+        while let Some(window) = reader.next_window().unwrap() {
+            windows.push(window);
+        }
+        windows
+    }
+
+    fn start_sample(window: &AudioWindow) -> usize {
+        (window.start_secs * RATE as f64).round() as usize
+    }
+
+    #[test]
+    fn windows_are_cut_in_pauses_without_overlap() {
+        let samples = [speech(3.0), silence(0.6), speech(4.4)].concat();
+        let windows = read_windows(&samples);
+
+        assert_eq!(windows.len(), 3);
+        let first = &windows[0];
+        assert_eq!(first.start_secs, 0.0);
+        assert_eq!(first.overlap_secs, 0.0);
+        assert!(first.samples.len() > 48000 && first.samples.len() < 57600);
+
+        // No pause in the second window, so it runs full length and the
+        // third repeats its last second
+        let second = &windows[1];
+        assert_eq!(start_sample(second), first.samples.len());
+        assert_eq!(second.overlap_secs, 0.0);
+        assert_eq!(second.samples.len(), 64000);
+
+        let third = &windows[2];
+        assert_eq!(third.overlap_secs, 1.0);
+        assert_eq!(start_sample(third), start_sample(second) + 48000);
+        assert_eq!(start_sample(third) + third.samples.len(), samples.len());
+    }
+
+    #[test]
+    fn windows_overlap_when_speech_never_pauses() {
+        let samples = speech(10.0);
+        let windows = read_windows(&samples);
+
+        let starts: Vec<usize> = windows.iter().map(start_sample).collect();
+        let overlaps: Vec<f64> = windows.iter().map(|w| w.overlap_secs).collect();
+        assert_eq!(starts, vec![0, 48000, 96000]);
+        assert_eq!(overlaps, vec![0.0, 1.0, 1.0]);
+        assert!(windows.iter().all(|w| w.samples.len() == 64000));
+    }
+
+    #[test]
+    fn short_files_are_a_single_window() {
+        let samples = speech(2.5);
+        let windows = read_windows(&samples);
+
+        assert_eq!(windows.len(), 1);
+        assert_eq!(windows[0].samples.len(), samples.len());
+    }
+}
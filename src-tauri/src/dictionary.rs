@@ -0,0 +1,197 @@
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Whisper-family prompts are capped at 224 tokens; stay well below that so
+/// the terms never get truncated mid-word (~4 chars per token)
+const MAX_PROMPT_CHARS: usize = 800;
+
+/// A word or phrase the speech engines should spell exactly as given
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DictionaryEntry {
+    /// Canonical spelling, e.g. "kubectl"
+    pub term: String,
+    /// Misrecognitions to rewrite to `term`, e.g. "cube control"
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// How the term is spoken, if it differs from its spelling, e.g. "kube cuddle"
+    #[serde(default)]
+    pub pronunciation: Option<String>,
+}
+
+/// Trim whitespace and drop empty terms, empty aliases and duplicate terms
+pub fn normalize(entries: Vec<DictionaryEntry>) -> Vec<DictionaryEntry> {
+    let mut normalized: Vec<DictionaryEntry> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let term = entry.term.trim().to_string();
+        if term.is_empty()
+            || normalized
+                .iter()
+                .any(|e| e.term.eq_ignore_ascii_case(&term))
+        {
+            continue;
+        }
+        let aliases = entry
+            .aliases
+            .iter()
+            .map(|a| a.trim().to_string())
+            .filter(|a| !a.is_empty() && !a.eq_ignore_ascii_case(&term))
+            .collect();
+        let pronunciation = entry
+            .pronunciation
+            .map(|p| p.trim().to_string())
+            .filter(|p| !p.is_empty());
+        normalized.push(DictionaryEntry {
+            term,
+            aliases,
+            pronunciation,
+        });
+    }
+    normalized
+}
+
+/// Vocabulary hint for engines that accept a prompt (Groq/OpenAI `prompt`,
+/// Whisper `initial_prompt`). Whisper models imitate the prompt's spelling,
+/// so a plain comma-separated list of terms works best.
+pub fn build_prompt(entries: &[DictionaryEntry]) -> Option<String> {
+    let mut prompt = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let separator = if prompt.is_empty() { "" } else { ", " };
+        if prompt.len() + separator.len() + entry.term.len() > MAX_PROMPT_CHARS {
+            println!(
+                "[Dictionary] Prompt limit reached, {} term(s) left out",
+                entries.len() - i
+            );
+            break;
+        }
+        prompt.push_str(separator);
+        prompt.push_str(&entry.term);
+    }
+    (!prompt.is_empty()).then_some(prompt)
+}
+
+/// Escaped pattern matching `phrase` as a whole word. `\b` is only added next
+/// to word characters so phrases like "C++" or ".NET" still match.
+pub fn whole_word_pattern(phrase: &str) -> String {
+    let is_word = |c: Option<char>| c.map(|c| c.is_alphanumeric() || c == '_').unwrap_or(false);
+    let start = if is_word(phrase.chars().next()) {
+        r"\b"
+    } else {
+        ""
+    };
+    let end = if is_word(phrase.chars().last()) {
+        r"\b"
+    } else {
+        ""
+    };
+    format!("{}{}{}", start, regex::escape(phrase), end)
+}
+
+/// Case-insensitive whole-word pattern
+fn phrase_regex(phrase: &str) -> Option<Regex> {
+    RegexBuilder::new(&whole_word_pattern(phrase))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+/// Rewrite aliases and spoken pronunciations to their dictionary term.
+/// Used for engines that can't be prompted (Parakeet). Longer phrases are
+/// replaced first so "cube control" wins over "cube".
+pub fn apply_replacements(text: &str, entries: &[DictionaryEntry]) -> String {
+    let mut replacements: Vec<(&str, &str)> = entries
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .aliases
+                .iter()
+                .map(String::as_str)
+                .chain(entry.pronunciation.as_deref())
+                .map(move |phrase| (phrase, entry.term.as_str()))
+        })
+        .collect();
+    replacements.sort_by_key(|(phrase, _)| std::cmp::Reverse(phrase.len()));
+
+    let mut result = text.to_string();
+    for (phrase, term) in replacements {
+        if let Some(re) = phrase_regex(phrase) {
+            result = re.replace_all(&result, NoExpand(term)).into_owned();
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(term: &str, aliases: &[&str], pronunciation: Option<&str>) -> DictionaryEntry {
+        DictionaryEntry {
+            term: term.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            pronunciation: pronunciation.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn normalize_trims_and_drops_empty_and_duplicate_entries() {
+        let normalized = normalize(vec![
+            entry(" kubectl ", &[" cube control ", "", "Kubectl"], Some("  ")),
+            entry("", &["orphan"], None),
+            entry("KUBECTL", &["duplicate"], None),
+            entry("Tauri", &[], Some(" tow ree ")),
+        ]);
+        assert_eq!(
+            normalized,
+            vec![
+                entry("kubectl", &["cube control"], None),
+                entry("Tauri", &[], Some("tow ree")),
+            ]
+        );
+    }
+
+    #[test]
+    fn build_prompt_lists_terms_within_the_limit() {
+        assert_eq!(build_prompt(&[]), None);
+        assert_eq!(
+            build_prompt(&[entry("kubectl", &[], None), entry("Tauri", &[], None)]),
+            Some("kubectl, Tauri".to_string())
+        );
+
+        let long = "x".repeat(MAX_PROMPT_CHARS - 2);
+        let prompt = build_prompt(&[entry(&long, &[], None), entry("kubectl", &[], None)]);
+        assert_eq!(prompt, Some(long));
+    }
+
+    #[test]
+    fn apply_replacements_prefers_longer_phrases_and_whole_words() {
+        let entries = vec![
+            entry("kubectl", &["cube control"], Some("kube cuddle")),
+            entry("Kubernetes", &["cube"], None),
+        ];
+        assert_eq!(
+            apply_replacements(
+                "Cube control and kube cuddle run on cube, not cubes",
+                &entries
+            ),
+            "kubectl and kubectl run on Kubernetes, not cubes"
+        );
+    }
+
+    #[test]
+    fn apply_replacements_matches_phrases_ending_in_symbols() {
+        let entries = vec![
+            entry("C++", &["see plus plus"], None),
+            entry(".NET", &["dot net"], None),
+        ];
+        assert_eq!(
+            apply_replacements("see plus plus and dot net", &entries),
+            "C++ and .NET"
+        );
+    }
+
+    #[test]
+    fn apply_replacements_inserts_terms_literally() {
+        let entries = vec![entry("$HOME", &["home dir"], None)];
+        assert_eq!(apply_replacements("cd home dir", &entries), "cd $HOME");
+    }
+}
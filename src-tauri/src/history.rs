@@ -0,0 +1,675 @@
+use crate::subtitles::{Segment, Word};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DB_FILE_NAME: &str = "history.sqlite3";
+
+/// Largest page the frontend may request in one call
+const MAX_PAGE_SIZE: u32 = 200;
+
+const SCHEMA: &str = r#"
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    source_name TEXT,
+    raw_text TEXT NOT NULL,
+    processed_text TEXT,
+    mode_id TEXT,
+    rules_applied INTEGER NOT NULL DEFAULT 0,
+    llm_provider TEXT,
+    engine TEXT NOT NULL,
+    language TEXT NOT NULL,
+    duration_seconds REAL NOT NULL,
+    word_count INTEGER NOT NULL,
+    segments TEXT,
+    words TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_history_created_at ON history (created_at DESC);
+"#;
+
+/// Where a history entry came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// Recorded with the global shortcut
+    Dictation,
+    File,
+    YouTube,
+}
+
+impl Source {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dictation => "dictation",
+            Self::File => "file",
+            Self::YouTube => "youtube",
+        }
+    }
+}
+
+/// Everything recorded about one transcription
+pub struct NewEntry {
+    pub source: Source,
+    /// File name or URL for file/YouTube transcriptions
+    pub source_name: Option<String>,
+    pub raw_text: String,
+    /// Text after a mode or rules were applied, if they changed anything
+    pub processed_text: Option<String>,
+    pub mode_id: Option<String>,
+    pub rules_applied: bool,
+    /// LLM provider and model that produced `processed_text`
+    pub llm_provider: Option<String>,
+    pub engine: String,
+    pub language: String,
+    pub duration_seconds: f64,
+    /// Timings of a file transcription, kept so it can be exported again
+    pub segments: Vec<Segment>,
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    /// Unix timestamp in milliseconds
+    pub created_at: i64,
+    pub source: String,
+    pub source_name: Option<String>,
+    pub raw_text: String,
+    pub processed_text: Option<String>,
+    pub mode_id: Option<String>,
+    pub rules_applied: bool,
+    pub llm_provider: Option<String>,
+    pub engine: String,
+    pub language: String,
+    pub duration_seconds: f64,
+    pub word_count: i64,
+    pub segments: Vec<Segment>,
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Number of entries matching the query across all pages
+    pub total: i64,
+}
+
+#[derive(Clone, Default)]
+pub struct HistoryState {
+    conn: Arc<Mutex<Option<Connection>>>,
+}
+
+impl HistoryState {
+    fn lock_conn(&self) -> Result<MutexGuard<'_, Option<Connection>>, String> {
+        self.conn
+            .lock()
+            .map_err(|e| format!("History lock poisoned: {}", e))
+    }
+
+    fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, String> {
+        let guard = self.lock_conn()?;
+        let conn = guard.as_ref().ok_or("History database is not available")?;
+        f(conn).map_err(|e| format!("History database error: {}", e))
+    }
+
+    fn with_conn_mut<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+    ) -> Result<T, String> {
+        let mut guard = self.lock_conn()?;
+        let conn = guard.as_mut().ok_or("History database is not available")?;
+        f(conn).map_err(|e| format!("History database error: {}", e))
+    }
+
+    /// Open (or create) the history database in the given app data directory
+    pub fn open(&self, app_data_dir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(app_data_dir)
+            .map_err(|e| format!("Failed to create app data dir: {}", e))?;
+        let path = app_data_dir.join(DB_FILE_NAME);
+        let conn = Connection::open(&path)
+            .map_err(|e| format!("Failed to open history database: {}", e))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|e| format!("Failed to initialize history database: {}", e))?;
+
+        *self.lock_conn()? = Some(conn);
+        println!("[History] Database opened at {:?}", path);
+        Ok(())
+    }
+
+    pub fn insert(&self, entry: NewEntry) -> Result<i64, String> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        self.insert_at(entry, created_at)
+    }
+
+    /// Insert with an explicit Unix timestamp in milliseconds
+    fn insert_at(&self, entry: NewEntry, created_at: i64) -> Result<i64, String> {
+        self.with_conn(|conn| insert_entry(conn, entry, created_at))
+    }
+
+    /// Newest-first page of entries, optionally filtered by a case-insensitive
+    /// substring match on the raw or processed text and by source
+    /// ("dictation", "file", "youtube")
+    pub fn list(
+        &self,
+        offset: u32,
+        limit: u32,
+        query: Option<&str>,
+        sources: Option<&[String]>,
+    ) -> Result<HistoryPage, String> {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+        let pattern = query
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .map(|q| format!("%{}%", escape_like(q)));
+        let sources = sources_json(sources)?;
+
+        let filter = format!(
+            "(?1 IS NULL OR raw_text LIKE ?1 ESCAPE '\\' OR processed_text LIKE ?1 ESCAPE '\\')
+             AND {}",
+            source_filter(2)
+        );
+
+        self.with_conn(|conn| {
+            let total: i64 = conn.query_row(
+                &format!("SELECT COUNT(*) FROM history WHERE {}", filter),
+                params![pattern, sources],
+                |row| row.get(0),
+            )?;
+
+            let mut stmt = conn.prepare(&format!(
+                "SELECT id, created_at, source, source_name, raw_text, processed_text, mode_id,
+                    rules_applied, llm_provider, engine, language, duration_seconds, word_count,
+                    segments, words
+                 FROM history WHERE {}
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT ?3 OFFSET ?4",
+                filter
+            ))?;
+            let entries = stmt
+                .query_map(params![pattern, sources, limit, offset], row_to_entry)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(HistoryPage { entries, total })
+        })
+    }
+
+    pub fn get(&self, id: i64) -> Result<Option<HistoryEntry>, String> {
+        self.with_conn(|conn| {
+            conn.query_row(
+                "SELECT id, created_at, source, source_name, raw_text, processed_text, mode_id,
+                    rules_applied, llm_provider, engine, language, duration_seconds, word_count,
+                    segments, words
+                 FROM history WHERE id = ?1",
+                params![id],
+                row_to_entry,
+            )
+            .optional()
+        })
+    }
+
+    pub fn delete(&self, id: i64) -> Result<(), String> {
+        self.with_conn(|conn| conn.execute("DELETE FROM history WHERE id = ?1", params![id]))?;
+        Ok(())
+    }
+
+    /// Delete every entry, or only those from the given sources
+    pub fn clear(&self, sources: Option<&[String]>) -> Result<(), String> {
+        let sources = sources_json(sources)?;
+        self.with_conn(|conn| {
+            conn.execute(
+                &format!("DELETE FROM history WHERE {}", source_filter(1)),
+                params![sources],
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Move file and YouTube transcriptions from the JSON list the frontend used
+    /// to keep in the settings store. Returns how many were imported. Runs in
+    /// one transaction, so a failed import leaves nothing behind to duplicate
+    /// when it is retried on the next start.
+    pub fn import_legacy(&self, json: &str) -> Result<usize, String> {
+        let items: Vec<LegacyItem> = serde_json::from_str(json)
+            .map_err(|e| format!("Failed to parse legacy history: {}", e))?;
+        let count = items.len();
+        self.with_conn_mut(|conn| {
+            let tx = conn.transaction()?;
+            // Oldest first so ids keep the original order
+            for item in items.into_iter().rev() {
+                let source = if item.source == "youtube" {
+                    Source::YouTube
+                } else {
+                    Source::File
+                };
+                let entry = NewEntry {
+                    source,
+                    source_name: Some(item.source_name),
+                    raw_text: item.result.raw_text,
+                    processed_text: item.result.processed_text,
+                    mode_id: None,
+                    rules_applied: false,
+                    llm_provider: None,
+                    engine: String::new(),
+                    language: String::new(),
+                    duration_seconds: item.result.duration_seconds,
+                    segments: item.result.segments,
+                    words: item.result.words,
+                };
+                insert_entry(&tx, entry, item.timestamp)?;
+            }
+            tx.commit()
+        })?;
+        Ok(count)
+    }
+}
+
+/// Entry of the frontend's old history list
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyItem {
+    timestamp: i64,
+    source: String,
+    source_name: String,
+    result: LegacyResult,
+}
+
+#[derive(Deserialize)]
+struct LegacyResult {
+    raw_text: String,
+    processed_text: Option<String>,
+    duration_seconds: f64,
+    #[serde(default)]
+    segments: Vec<Segment>,
+    #[serde(default)]
+    words: Vec<Word>,
+}
+
+fn insert_entry(conn: &Connection, entry: NewEntry, created_at: i64) -> rusqlite::Result<i64> {
+    let word_count = entry
+        .processed_text
+        .as_deref()
+        .unwrap_or(&entry.raw_text)
+        .split_whitespace()
+        .count() as i64;
+
+    conn.execute(
+        "INSERT INTO history (created_at, source, source_name, raw_text, processed_text,
+            mode_id, rules_applied, llm_provider, engine, language, duration_seconds,
+            word_count, segments, words)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+        params![
+            created_at,
+            entry.source.as_str(),
+            entry.source_name,
+            entry.raw_text,
+            entry.processed_text,
+            entry.mode_id,
+            entry.rules_applied,
+            entry.llm_provider,
+            entry.engine,
+            entry.language,
+            entry.duration_seconds,
+            word_count,
+            json_column(&entry.segments)?,
+            json_column(&entry.words)?,
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Matches every source when parameter `index` is NULL, else the sources in
+/// the JSON array bound to it
+fn source_filter(index: usize) -> String {
+    format!(
+        "(?{0} IS NULL OR source IN (SELECT value FROM json_each(?{0})))",
+        index
+    )
+}
+
+fn sources_json(sources: Option<&[String]>) -> Result<Option<String>, String> {
+    sources
+        .map(|s| serde_json::to_string(s).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+fn row_to_entry(row: &rusqlite::Row<'_>) -> rusqlite::Result<HistoryEntry> {
+    Ok(HistoryEntry {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        source: row.get(2)?,
+        source_name: row.get(3)?,
+        raw_text: row.get(4)?,
+        processed_text: row.get(5)?,
+        mode_id: row.get(6)?,
+        rules_applied: row.get(7)?,
+        llm_provider: row.get(8)?,
+        engine: row.get(9)?,
+        language: row.get(10)?,
+        duration_seconds: row.get(11)?,
+        word_count: row.get(12)?,
+        segments: from_json_column(row, 13)?,
+        words: from_json_column(row, 14)?,
+    })
+}
+
+/// JSON for a list column; NULL when it is empty, as for every dictation
+fn json_column<T: Serialize>(items: &[T]) -> rusqlite::Result<Option<String>> {
+    if items.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(items)
+        .map(Some)
+        .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+fn from_json_column<T: serde::de::DeserializeOwned>(
+    row: &rusqlite::Row<'_>,
+    index: usize,
+) -> rusqlite::Result<Vec<T>> {
+    let Some(json) = row.get::<_, Option<String>>(index)? else {
+        return Ok(Vec::new());
+    };
+    serde_json::from_str(&json).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+/// Escape LIKE wildcards so user queries match literally
+fn escape_like(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_history(dir: &Path) -> HistoryState {
+        let history = HistoryState::default();
+        history.open(dir).unwrap();
+        history
+    }
+
+    fn entry(source: Source, raw_text: &str) -> NewEntry {
+        NewEntry {
+            source,
+            source_name: None,
+            raw_text: raw_text.to_string(),
+            processed_text: None,
+            mode_id: None,
+            rules_applied: false,
+            llm_provider: None,
+            engine: "Groq".to_string(),
+            language: "en".to_string(),
+            duration_seconds: 2.0,
+            segments: Vec::new(),
+            words: Vec::new(),
+        }
+    }
+
+    fn raw_texts(page: &HistoryPage) -> Vec<&str> {
+        page.entries.iter().map(|e| e.raw_text.as_str()).collect()
+    }
+
+    #[test]
+    fn insert_and_get_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+
+        let id = history
+            .insert(NewEntry {
+                processed_text: Some("Hello there, world.".to_string()),
+                mode_id: Some("email".to_string()),
+                llm_provider: Some("OpenAI / gpt-4o-mini".to_string()),
+                ..entry(Source::Dictation, "hello there world")
+            })
+            .unwrap();
+
+        let stored = history.get(id).unwrap().unwrap();
+        assert_eq!(stored.source, "dictation");
+        assert_eq!(stored.raw_text, "hello there world");
+        assert_eq!(
+            stored.processed_text.as_deref(),
+            Some("Hello there, world.")
+        );
+        assert_eq!(stored.mode_id.as_deref(), Some("email"));
+        assert_eq!(stored.llm_provider.as_deref(), Some("OpenAI / gpt-4o-mini"));
+        assert_eq!(stored.word_count, 3);
+        assert!(history.get(id + 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn list_pages_newest_first_with_the_total() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        for (i, text) in ["one", "two", "three", "four", "five"].iter().enumerate() {
+            history
+                .insert_at(entry(Source::Dictation, text), i as i64)
+                .unwrap();
+        }
+
+        let first = history.list(0, 2, None, None).unwrap();
+        assert_eq!(raw_texts(&first), ["five", "four"]);
+        assert_eq!(first.total, 5);
+
+        let last = history.list(4, 2, None, None).unwrap();
+        assert_eq!(raw_texts(&last), ["one"]);
+        assert_eq!(last.total, 5);
+    }
+
+    #[test]
+    fn list_searches_raw_and_processed_text_case_insensitively() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        history
+            .insert_at(entry(Source::Dictation, "Meeting notes"), 1)
+            .unwrap();
+        history
+            .insert_at(
+                NewEntry {
+                    processed_text: Some("Agenda for the MEETING".to_string()),
+                    ..entry(Source::Dictation, "agenda")
+                },
+                2,
+            )
+            .unwrap();
+        history
+            .insert_at(entry(Source::Dictation, "shopping list"), 3)
+            .unwrap();
+        history
+            .insert_at(entry(Source::Dictation, "100% done"), 4)
+            .unwrap();
+
+        let page = history.list(0, 10, Some("  meeting "), None).unwrap();
+        assert_eq!(raw_texts(&page), ["agenda", "Meeting notes"]);
+        assert_eq!(page.total, 2);
+
+        // Wildcards in the query match literally
+        let page = history.list(0, 10, Some("%"), None).unwrap();
+        assert_eq!(raw_texts(&page), ["100% done"]);
+
+        // A blank query matches everything
+        assert_eq!(history.list(0, 10, Some("   "), None).unwrap().total, 4);
+    }
+
+    #[test]
+    fn list_filters_by_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        history
+            .insert_at(entry(Source::Dictation, "dictated"), 1)
+            .unwrap();
+        history
+            .insert_at(entry(Source::File, "from a file"), 2)
+            .unwrap();
+        history
+            .insert_at(entry(Source::YouTube, "from a video"), 3)
+            .unwrap();
+
+        let sources = ["file".to_string(), "youtube".to_string()];
+        let page = history.list(0, 10, None, Some(&sources[..])).unwrap();
+        assert_eq!(raw_texts(&page), ["from a video", "from a file"]);
+        assert_eq!(page.total, 2);
+
+        let page = history
+            .list(0, 10, Some("from"), Some(&sources[..1]))
+            .unwrap();
+        assert_eq!(raw_texts(&page), ["from a file"]);
+    }
+
+    #[test]
+    fn delete_removes_a_single_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        let kept = history.insert(entry(Source::Dictation, "kept")).unwrap();
+        let deleted = history.insert(entry(Source::Dictation, "deleted")).unwrap();
+
+        history.delete(deleted).unwrap();
+
+        assert!(history.get(deleted).unwrap().is_none());
+        assert!(history.get(kept).unwrap().is_some());
+    }
+
+    #[test]
+    fn clear_with_sources_keeps_the_other_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        history
+            .insert_at(entry(Source::Dictation, "dictated"), 1)
+            .unwrap();
+        history
+            .insert_at(entry(Source::File, "from a file"), 2)
+            .unwrap();
+        history
+            .insert_at(entry(Source::YouTube, "from a video"), 3)
+            .unwrap();
+
+        let sources = ["file".to_string(), "youtube".to_string()];
+        history.clear(Some(&sources[..])).unwrap();
+        assert_eq!(
+            raw_texts(&history.list(0, 10, None, None).unwrap()),
+            ["dictated"]
+        );
+
+        history.clear(None).unwrap();
+        assert_eq!(history.list(0, 10, None, None).unwrap().total, 0);
+    }
+
+    #[test]
+    fn segments_and_words_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        let timed = history
+            .insert(NewEntry {
+                segments: vec![Segment {
+                    start: 0.0,
+                    end: 1.5,
+                    text: "hello world".to_string(),
+                }],
+                words: vec![Word {
+                    start: 0.2,
+                    end: 0.6,
+                    text: "hello".to_string(),
+                    probability: Some(0.9),
+                }],
+                ..entry(Source::File, "hello world")
+            })
+            .unwrap();
+        let untimed = history.insert(entry(Source::Dictation, "plain")).unwrap();
+
+        let stored = history.get(timed).unwrap().unwrap();
+        assert_eq!(stored.segments.len(), 1);
+        assert_eq!(stored.segments[0].end, 1.5);
+        assert_eq!(stored.segments[0].text, "hello world");
+        assert_eq!(stored.words.len(), 1);
+        assert_eq!(stored.words[0].probability, Some(0.9));
+
+        let stored = history.get(untimed).unwrap().unwrap();
+        assert!(stored.segments.is_empty());
+        assert!(stored.words.is_empty());
+    }
+
+    #[test]
+    fn import_legacy_keeps_timings() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        let json = r#"[
+            {"timestamp": 1000, "source": "file", "sourceName": "talk.mp3",
+             "result": {"raw_text": "talk", "processed_text": null, "duration_seconds": 30.0,
+                        "segments": [{"start": 0.0, "end": 2.0, "text": "talk"}],
+                        "words": [{"start": 0.1, "end": 0.5, "text": "talk"}]}}
+        ]"#;
+
+        history.import_legacy(json).unwrap();
+
+        let page = history.list(0, 10, None, None).unwrap();
+        assert_eq!(page.entries[0].segments[0].end, 2.0);
+        assert_eq!(page.entries[0].words[0].text, "talk");
+        assert_eq!(page.entries[0].words[0].probability, None);
+    }
+
+    #[test]
+    fn import_legacy_keeps_order_timestamps_and_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+        // The frontend kept the newest item first
+        let json = r#"[
+            {"timestamp": 2000, "source": "youtube", "sourceName": "https://youtu.be/x",
+             "result": {"raw_text": "video", "processed_text": "Video.", "duration_seconds": 60.0}},
+            {"timestamp": 1000, "source": "file", "sourceName": "talk.mp3",
+             "result": {"raw_text": "talk", "processed_text": null, "duration_seconds": 30.0}}
+        ]"#;
+
+        assert_eq!(history.import_legacy(json).unwrap(), 2);
+
+        let page = history.list(0, 10, None, None).unwrap();
+        assert_eq!(raw_texts(&page), ["video", "talk"]);
+        let video = &page.entries[0];
+        assert_eq!(video.source, "youtube");
+        assert_eq!(video.source_name.as_deref(), Some("https://youtu.be/x"));
+        assert_eq!(video.processed_text.as_deref(), Some("Video."));
+        assert_eq!(video.created_at, 2000);
+        let talk = &page.entries[1];
+        assert_eq!(talk.source, "file");
+        assert_eq!(talk.created_at, 1000);
+        // Imported oldest first, so ids follow the original order
+        assert!(talk.id < video.id);
+    }
+
+    #[test]
+    fn import_legacy_rejects_malformed_json_without_importing() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = open_history(dir.path());
+
+        assert!(history.import_legacy("not json").is_err());
+        assert_eq!(history.list(0, 10, None, None).unwrap().total, 0);
+    }
+
+    #[test]
+    fn escape_like_escapes_wildcards_and_the_escape_character() {
+        assert_eq!(escape_like("plain text"), "plain text");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("snake_case"), "snake\\_case");
+        assert_eq!(escape_like(r"C:\dir"), r"C:\\dir");
+    }
+
+    #[test]
+    fn escape_like_leaves_multibyte_text_intact() {
+        assert_eq!(escape_like("zażółć_%"), "zażółć\\_\\%");
+    }
+}
@@ -1,17 +1,35 @@
+mod app_profiles;
 mod audio;
+mod chunking;
+mod clipboard;
+mod decode;
+mod dictionary;
 mod groq;
+mod history;
 mod keyboard_lock;
 mod llm;
+mod local_rules;
+mod openai_stt;
 mod parakeet;
+mod retry_queue;
+mod stt;
+mod subtitles;
+#[cfg(test)]
+mod test_audio;
 mod transcribe;
+mod typing;
+mod vad;
+mod voice_commands;
+mod wav;
 mod whisper;
 
 use audio::{AudioCaptureHandle, AudioDevice};
 #[cfg(not(target_os = "macos"))]
 use enigo::{Enigo, Key, Keyboard, Settings};
-use groq::GroqState;
+use groq::{AppendOutcome, GroqState};
 use once_cell::sync::Lazy;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc;
 use std::sync::Mutex;
 use tauri::{
@@ -36,6 +54,9 @@ const FLOATING_WINDOW_DEFAULT_Y: f64 = 8.0;
 // Clipboard propagation delay (ms) — macOS NSPasteboard doesn't propagate writes instantly;
 // pasting too soon after writing may read stale contents.
 const CLIPBOARD_PROPAGATION_DELAY_MS: u64 = 150;
+/// How long the target app gets to read the pasted text before the previous
+/// clipboard contents are put back
+const DEFAULT_CLIPBOARD_RESTORE_DELAY_MS: u64 = 500;
 
 // Statistics calculation constants
 const AVERAGE_TYPING_WPM: f64 = 40.0; // Average typing speed for time-saved calculations
@@ -66,6 +87,33 @@ mod store_keys {
     pub const STT_PROVIDER: &str = "sttProvider";
     pub const PURE_PASTE_ENABLED: &str = "purePasteEnabled";
     pub const PURE_PASTE_SHORTCUT: &str = "purePasteShortcut";
+    pub const VAD_ENABLED: &str = "vadEnabled";
+    /// Legacy frontend history, imported into the history database on startup
+    pub const TRANSCRIPTION_HISTORY: &str = "transcriptionHistory";
+    pub const AUTO_STOP_ENABLED: &str = "autoStopEnabled";
+    pub const AUTO_STOP_SILENCE_SECONDS: &str = "autoStopSilenceSeconds";
+    pub const AUTO_STOP_THRESHOLD: &str = "autoStopThreshold";
+    pub const LONG_RECORDING_ENABLED: &str = "longRecordingEnabled";
+    pub const SHORTCUT_MODE: &str = "shortcutMode";
+    pub const CUSTOM_LLM_BASE_URL: &str = "customLlmBaseUrl";
+    pub const CUSTOM_LLM_API_KEY: &str = "customLlmApiKey";
+    pub const CUSTOM_LLM_MODEL: &str = "customLlmModel";
+    pub const CUSTOM_STT_BASE_URL: &str = "customSttBaseUrl";
+    pub const CUSTOM_STT_API_KEY: &str = "customSttApiKey";
+    pub const CUSTOM_STT_MODEL: &str = "customSttModel";
+    pub const RERUN_SHORTCUT: &str = "rerunShortcut";
+    pub const DICTIONARY: &str = "dictionary";
+    pub const LOCAL_RULES: &str = "localRules";
+    pub const VOICE_COMMANDS_ENABLED: &str = "voiceCommandsEnabled";
+    pub const VOICE_COMMANDS: &str = "voiceCommands";
+    pub const INSERTION_METHOD: &str = "insertionMethod";
+    pub const MODE_INSERTION_METHODS: &str = "modeInsertionMethods";
+    pub const RESTORE_CLIPBOARD: &str = "restoreClipboard";
+    pub const CLIPBOARD_RESTORE_DELAY_MS: &str = "clipboardRestoreDelayMs";
+    pub const APP_PROFILES: &str = "appProfiles";
+    pub const MODE_SHORTCUTS: &str = "modeShortcuts";
+    pub const STT_FALLBACK_CHAIN: &str = "sttFallbackChain";
+    pub const LLM_FALLBACK_CHAIN: &str = "llmFallbackChain";
 }
 
 // Built-in mode prompts
@@ -99,9 +147,61 @@ NEVER change the message's intent. Output ONLY the formatted email."#;
 
 const DEFAULT_PURE_PASTE_SHORTCUT: &str = "CommandOrControl+Shift+V";
 
+// Auto-stop defaults (hands-free mode)
+const DEFAULT_AUTO_STOP_SILENCE_SECONDS: f64 = 2.0;
+const MIN_AUTO_STOP_SILENCE_SECONDS: f64 = 0.5;
+
+// Disk-backed recordings are transcribed in pieces of at most this length
+// (5 min of 24kHz PCM16 is ~14MB, under Groq's upload limit)
+const LONG_RECORDING_CHUNK_SECONDS: f64 = 300.0;
+
+// Hold-to-talk: releases sooner than this are treated as accidental taps
+const MIN_HOLD_DURATION_MS: u128 = 300;
+/// How often the retry queue is checked for recordings due for another attempt
+const RETRY_QUEUE_POLL_SECONDS: u64 = 30;
+
 static IS_RECORDING: AtomicBool = AtomicBool::new(false);
+/// Incremented for every recording, so deferred work can tell whether the
+/// recording it belongs to is still the current one
+static RECORDING_SESSION: AtomicU64 = AtomicU64::new(0);
+/// Ids of the recording shortcuts (main and modes) physically held down, for
+/// hold-to-talk mode
+static HELD_SHORTCUTS: Lazy<Mutex<HashSet<u32>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+static RECORDING_STARTED_AT: Lazy<Mutex<Option<std::time::Instant>>> =
+    Lazy::new(|| Mutex::new(None));
+/// Serializes hold-to-talk press/release handling so a quick release can't
+/// race a recording that is still starting up
+static HOLD_TO_TALK_LOCK: Lazy<tokio::sync::Mutex<()>> =
+    Lazy::new(|| tokio::sync::Mutex::new(()));
 static REGISTERED_PURE_PASTE_SHORTCUT: Lazy<Mutex<Option<String>>> =
     Lazy::new(|| Mutex::new(None));
+static REGISTERED_RERUN_SHORTCUT: Lazy<Mutex<Option<String>>> =
+    Lazy::new(|| Mutex::new(None));
+static REGISTERED_MODE_SHORTCUTS: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+/// Mode requested by the mode shortcut that started the current recording
+static RECORDING_MODE: Lazy<Mutex<Option<String>>> = Lazy::new(|| Mutex::new(None));
+/// Serializes retry-queue processing so the background worker and a manual
+/// retry never transcribe the same recording twice
+static RETRY_QUEUE_LOCK: Lazy<tokio::sync::Mutex<()>> =
+    Lazy::new(|| tokio::sync::Mutex::new(()));
+
+/// The most recent shortcut dictation, kept so it can be re-processed with a
+/// different mode without recording again
+#[derive(Clone)]
+struct LastDictation {
+    raw_transcript: String,
+    engine: String,
+    language: String,
+    duration_seconds: f64,
+}
+
+static LAST_DICTATION: Lazy<Mutex<Option<LastDictation>>> = Lazy::new(|| Mutex::new(None));
+/// Set while the last dictation is being re-run
+static IS_RERUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Profile of the app that had focus when the current recording started
+static ACTIVE_APP_PROFILE: Lazy<Mutex<Option<app_profiles::AppProfile>>> =
+    Lazy::new(|| Mutex::new(None));
 
 pub struct AudioCaptureState {
     handle: AudioCaptureHandle,
@@ -124,7 +224,38 @@ fn list_audio_devices() -> Result<Vec<AudioDevice>, String> {
 
 #[tauri::command]
 async fn start_recording(app: AppHandle) -> Result<(), String> {
+    start_recording_in_mode(app, None).await
+}
+
+/// Start recording; `mode_id` (a mode id, "rules" or "none") overrides the
+/// selected mode for this recording only
+async fn start_recording_in_mode(app: AppHandle, mode_id: Option<String>) -> Result<(), String> {
+    // Detect the target app before the floating window can take focus
+    let profile = detect_app_profile(&app);
+    start_recording_with_profile(app, mode_id, profile).await
+}
+
+/// Start recording for the app profile detected when the shortcut fired
+async fn start_recording_with_profile(
+    app: AppHandle,
+    mode_id: Option<String>,
+    profile: Option<app_profiles::AppProfile>,
+) -> Result<(), String> {
     IS_RECORDING.store(true, Ordering::SeqCst);
+    let session = RECORDING_SESSION.fetch_add(1, Ordering::SeqCst) + 1;
+    if let Ok(mut started_at) = RECORDING_STARTED_AT.lock() {
+        *started_at = Some(std::time::Instant::now());
+    }
+
+    if let Ok(mut active) = ACTIVE_APP_PROFILE.lock() {
+        *active = profile;
+    }
+    if let Some(ref mode_id) = mode_id {
+        app.emit("recording-mode", mode_id).ok();
+    }
+    if let Ok(mut mode) = RECORDING_MODE.lock() {
+        *mode = mode_id;
+    }
 
     // Register cancel shortcut only while recording
     let cancel_shortcut_str = get_cancel_shortcut_from_store(&app);
@@ -136,6 +267,12 @@ async fn start_recording(app: AppHandle) -> Result<(), String> {
     let groq_state = app.state::<GroqState>();
     groq_state.clear_buffer();
 
+    // Long-recording mode streams to disk from the start; otherwise the
+    // recording only moves to disk if it outgrows the in-memory buffer
+    if is_long_recording_enabled(&app) {
+        groq_state.start_disk_recording()?;
+    }
+
     // Start native audio capture
     let device_id = get_store_string(&app, store_keys::MICROPHONE_DEVICE_ID);
     let audio_state = app.state::<AudioCaptureState>();
@@ -143,22 +280,57 @@ async fn start_recording(app: AppHandle) -> Result<(), String> {
     // Create channels for audio data and levels
     let (audio_tx, audio_rx) = mpsc::channel::<Vec<u8>>();
     let (level_tx, level_rx) = mpsc::channel::<f32>();
+    let (warning_tx, warning_rx) = mpsc::channel::<String>();
+
+    // Hands-free mode: the audio thread signals once trailing silence is long enough
+    let auto_stop = if is_auto_stop_enabled(&app) {
+        let (silence_tx, silence_rx) = mpsc::channel::<()>();
+        let app_clone = app.clone();
+        std::thread::spawn(move || {
+            // recv() errors once the capture ends, which is the normal manual-stop
+            // path; a stop racing this one is resolved inside stop_recording
+            if silence_rx.recv().is_ok() {
+                tauri::async_runtime::spawn(async move {
+                    // The user may have stopped this recording and started
+                    // another one before the task got to run
+                    if RECORDING_SESSION.load(Ordering::SeqCst) != session {
+                        return;
+                    }
+                    println!("[Dictato] Auto-stopping recording after trailing silence");
+                    if let Err(e) = stop_recording(app_clone).await {
+                        eprintln!("Failed to auto-stop recording: {}", e);
+                    }
+                });
+            }
+        });
+        Some(get_auto_stop_config(&app, silence_tx))
+    } else {
+        None
+    };
 
     // Start the audio capture
     audio_state
         .handle
-        .start(device_id, audio_tx, level_tx)?;
+        .start(device_id, audio_tx, level_tx, warning_tx, auto_stop)?;
 
     // Spawn task to receive audio data and store in buffer
     let groq_state_clone = app.state::<GroqState>().inner().clone();
+    let app_for_receiver = app.clone();
     let receiver_handle = std::thread::spawn(move || {
         let mut chunks_received: usize = 0;
         let mut total_bytes: usize = 0;
         while let Ok(audio_chunk) = audio_rx.recv() {
             chunks_received += 1;
             total_bytes += audio_chunk.len();
-            if let Err(e) = groq_state_clone.append_audio(audio_chunk) {
-                eprintln!("[Audio] Failed to append audio: {}", e);
+            match groq_state_clone.append_audio(audio_chunk) {
+                Ok(AppendOutcome::SpilledToDisk) => {
+                    println!("[Audio] In-memory buffer full, continuing recording on disk");
+                    app_for_receiver
+                        .emit("recording-warning", "Long recording - continuing on disk")
+                        .ok();
+                }
+                Ok(AppendOutcome::Buffered) => {}
+                Err(e) => eprintln!("[Audio] Failed to append audio: {}", e),
             }
         }
         println!(
@@ -183,12 +355,25 @@ async fn start_recording(app: AppHandle) -> Result<(), String> {
         }
     });
 
+    // Capture problems that don't end the recording are shown while recording
+    let app_clone = app.clone();
+    std::thread::spawn(move || {
+        while let Ok(warning) = warning_rx.recv() {
+            eprintln!("[Audio] {}", warning);
+            app_clone.emit("recording-warning", warning).ok();
+        }
+    });
+
     Ok(())
 }
 
 #[tauri::command]
 async fn stop_recording(app: AppHandle) -> Result<(), String> {
-    IS_RECORDING.store(false, Ordering::SeqCst);
+    // Only one caller gets to stop: auto-stop, hold release and the shortcut
+    // can race, and each would otherwise transcribe the recording again
+    if !IS_RECORDING.swap(false, Ordering::SeqCst) {
+        return Ok(());
+    }
     app.emit("recording-state", false).ok();
 
     // Unregister cancel shortcut since recording stopped
@@ -211,157 +396,173 @@ async fn stop_recording(app: AppHandle) -> Result<(), String> {
     }
 
     let groq_state = app.state::<GroqState>();
+    let disk_recording = groq_state.finish_disk_recording()?;
     let audio_data = groq_state.get_buffer()?;
+    let recording_seconds = match disk_recording {
+        Some(ref recording) => recording.duration_secs(),
+        None => audio_data.len() as f64 / (audio::TARGET_SAMPLE_RATE as f64 * 2.0),
+    };
 
-    println!("[Dictato] Audio buffer size: {} bytes", audio_data.len());
+    match disk_recording {
+        Some(ref recording) => println!(
+            "[Dictato] Disk recording: {:.1}s at {:?}",
+            recording.duration_secs(),
+            recording.path()
+        ),
+        None => println!("[Dictato] Audio buffer size: {} bytes", audio_data.len()),
+    }
 
     groq_state.clear_buffer();
 
-    let stt_provider = get_stt_provider_from_store(&app);
-    let llm_provider = get_llm_provider_from_store(&app);
-    let llm_api_key = get_llm_api_key_for_provider(&app, &llm_provider);
-    let llm_model = get_llm_model_for_provider(&app, &llm_provider);
-    let language = get_language_from_store(&app);
-    let transcript = if audio_data.is_empty() {
-        println!("[Dictato] Skipping transcription: audio buffer empty");
-        String::new()
-    } else if stt_provider == parakeet::SttProvider::Parakeet {
-        println!("[Dictato] Transcribing {} bytes locally with Parakeet", audio_data.len());
-        app.emit("processing-state", true).ok();
-        parakeet::set_transcribing(true);
-        let parakeet_state = app.state::<parakeet::ParakeetState>();
-        let state_clone = parakeet_state.inner().clone();
-        let result = tokio::task::spawn_blocking(move || {
-            let r = parakeet::transcribe_pcm16(&state_clone, audio_data);
-            parakeet::set_transcribing(false);
-            r
-        })
-        .await
-        .map_err(|e| {
-            parakeet::set_transcribing(false);
-            format!("Transcription task failed: {}", e)
-        })?;
-        match result {
-            Ok(text) => text,
-            Err(e) => {
-                app.emit("processing-state", false).ok();
-                return Err(e);
-            }
-        }
-    } else if stt_provider == parakeet::SttProvider::Whisper {
-        println!("[Dictato] Transcribing {} bytes locally with Whisper", audio_data.len());
-        app.emit("processing-state", true).ok();
-        parakeet::set_transcribing(true);
-        let whisper_state = app.state::<whisper::WhisperState>();
-        let state_clone = whisper_state.inner().clone();
-        let lang = language.clone();
-        let result = tokio::task::spawn_blocking(move || {
-            let r = whisper::transcribe_pcm16(&state_clone, audio_data, &lang);
-            parakeet::set_transcribing(false);
-            r
-        })
-        .await
-        .map_err(|e| {
-            parakeet::set_transcribing(false);
-            format!("Transcription task failed: {}", e)
-        })?;
-        match result {
-            Ok(text) => text,
-            Err(e) => {
-                app.emit("processing-state", false).ok();
-                return Err(e);
-            }
-        }
-    } else {
-        let groq_api_key = get_groq_api_key_from_store(&app).unwrap_or_default();
-        if groq_api_key.is_empty() {
-            println!("[Dictato] Skipping transcription: groq_api_key_empty");
-            String::new()
+    // Trim leading/trailing silence so engines don't pay for (or hallucinate on) it.
+    // Disk recordings are transcribed chunk by chunk and never loaded whole.
+    let audio_data = if !audio_data.is_empty() && disk_recording.is_none() && is_vad_enabled(&app) {
+        let trimmed = vad::trim_silence_pcm16(
+            &audio_data,
+            audio::TARGET_SAMPLE_RATE,
+            &vad::VadConfig::default(),
+        );
+        let spans: Vec<String> = trimmed
+            .segments
+            .iter()
+            .map(|seg| {
+                format!(
+                    "{:.2}-{:.2}s",
+                    seg.start_secs(audio::TARGET_SAMPLE_RATE),
+                    seg.end_secs(audio::TARGET_SAMPLE_RATE)
+                )
+            })
+            .collect();
+        println!(
+            "[Dictato] VAD: {} speech segment(s) [{}], trimmed {} -> {} bytes",
+            trimmed.segments.len(),
+            spans.join(", "),
+            audio_data.len(),
+            trimmed.pcm16.len()
+        );
+        app.emit("speech-segments", &trimmed.segments).ok();
+        if trimmed.pcm16.is_empty() {
+            // Quiet speech can fall under the detector; let the engine decide
+            // rather than dropping the dictation
+            println!("[Dictato] VAD found no speech, transcribing the untrimmed audio");
+            audio_data
         } else {
-            println!("[Dictato] Sending {} bytes to Groq API", audio_data.len());
-            app.emit("processing-state", true).ok();
-            let result = groq::transcribe(&groq_api_key, audio_data, &language).await;
-            match result {
-                Ok(text) => text,
-                Err(e) => {
-                    app.emit("processing-state", false).ok();
-                    return Err(e);
-                }
-            }
+            trimmed.pcm16
         }
+    } else {
+        audio_data
     };
 
-    // Apply mode transformation or rules (modes take priority over rules)
-    // Uses the selected LLM provider for processing
-    let mut had_llm_error = false;
-    let provider_name = get_llm_provider_name(&llm_provider);
-    let final_text = if !transcript.is_empty() {
-        let skip_rules = should_skip_rules(&app);
-        if skip_rules {
-            println!("[Dictato] Transformation skipped for this recording");
-            transcript
-        } else if let Some(mode_id) = get_active_mode_from_store(&app) {
-            // Mode is active - get prompt and apply transformation (rules are ignored)
-            if let Some(prompt) = get_mode_prompt_from_store(&app, &mode_id) {
-                // Check for LLM API key
-                if let Some(ref llm_key) = llm_api_key {
-                    app.emit("processing-message", "Applying mode...").ok();
-                    match llm::process_with_prompt(&llm_provider, llm_key, &llm_model, &transcript, &prompt).await {
-                        Ok(processed) => {
-                            println!("[Dictato] Mode '{}' applied successfully using {}", mode_id, provider_name);
-                            processed
-                        }
-                        Err(e) => {
-                            eprintln!("[Dictato] Mode processing failed, using raw transcript: {}", e);
-                            had_llm_error = true;
-                            show_error(&app, &format_llm_error(&e));
-                            transcript
-                        }
-                    }
-                } else {
-                    // No API key for selected provider - show error and return raw transcript
-                    had_llm_error = true;
-                    show_error(&app, &format!("No {} API key - mode skipped. Raw transcription copied. Add key in Settings to use modes.", provider_name));
-                    transcript
-                }
-            } else {
-                println!("[Dictato] Mode '{}' not found, using raw transcript", mode_id);
-                transcript
+    let profile = ACTIVE_APP_PROFILE.lock().ok().and_then(|mut p| p.take());
+    let shortcut_mode = RECORDING_MODE.lock().ok().and_then(|mut m| m.take());
+    // A mode shortcut or the app profile picks the mode instead of the selected one
+    let mode_override =
+        shortcut_mode.or_else(|| profile.as_ref().and_then(|p| p.mode_id.clone()));
+    let engine = get_stt_engine_for_profile(&app, profile.as_ref())?;
+    let options = stt::TranscribeOptions {
+        language: profile
+            .as_ref()
+            .and_then(|p| p.language.clone())
+            .unwrap_or_else(|| get_language_from_store(&app)),
+        dictionary: get_dictionary_from_store(&app),
+        progress: None,
+    };
+    if !engine.supports_language(&options.language) {
+        println!(
+            "[Dictato] {} does not list language '{}' as supported, transcribing anyway",
+            engine.display_name(),
+            options.language
+        );
+    }
+    // The selected engine first, then any ready fallbacks from the chain
+    let engines: Vec<_> = get_stt_engine_chain(&app, engine.clone())
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, candidate)| match candidate.check_ready(&app) {
+            Ok(()) => Some(candidate),
+            Err(e) if index > 0 => {
+                eprintln!(
+                    "[Dictato] Skipping STT fallback {}: {}",
+                    candidate.display_name(),
+                    e
+                );
+                None
             }
-        } else {
-            // No mode active - apply rules if any are enabled
-            let rules = get_transcription_rules_from_store(&app);
-            let has_enabled_rules = rules.iter().any(|r| r.enabled);
-            if has_enabled_rules {
-                // Check for LLM API key
-                if let Some(ref llm_key) = llm_api_key {
-                    app.emit("processing-message", "Applying rules...").ok();
-                    match llm::process_with_rules(&llm_provider, llm_key, &llm_model, &transcript, rules).await {
-                        Ok(processed) => {
-                            println!("[Dictato] Rules applied successfully using {}", provider_name);
-                            processed
-                        }
-                        Err(e) => {
-                            eprintln!("[Dictato] Rule processing failed, using raw transcript: {}", e);
-                            had_llm_error = true;
-                            show_error(&app, &format_llm_error(&e));
-                            transcript
+            Err(e) => {
+                println!("[Dictato] Skipping {}: {}", candidate.display_name(), e);
+                None
+            }
+        })
+        .collect();
+    let (transcript, engine) = if audio_data.is_empty() && disk_recording.is_none() {
+        println!("[Dictato] Skipping transcription: audio buffer empty");
+        (String::new(), engine)
+    } else if engines.is_empty() {
+        println!("[Dictato] Skipping transcription: no STT engine is ready");
+        (String::new(), engine)
+    } else {
+        app.emit("processing-state", true).ok();
+        let result = transcribe_with_fallback(
+            &app,
+            &engines,
+            &audio_data,
+            disk_recording.as_ref(),
+            &options,
+        )
+        .await;
+        match result {
+            Ok((text, used)) => (text, used),
+            Err(failure) => {
+                app.emit("processing-state", false).ok();
+                // Remote engines can fail transiently; queue the recording for
+                // a later retry with the engine that failed that way, instead
+                // of losing the dictation. Errors that need the user (bad key,
+                // rejected request) won't go away by retrying, so those are
+                // reported as usual.
+                if let Some((failed, error)) = failure.retryable() {
+                    let meta = retry_queue::NewQueuedRecording {
+                        provider: failed.provider().as_store_value().to_string(),
+                        engine: failed.display_name().to_string(),
+                        language: options.language.clone(),
+                        duration_seconds: recording_seconds,
+                        mode_id: transformation_from_store(&app, mode_override.as_deref())
+                            .mode_id()
+                            .to_string(),
+                        error: error.clone(),
+                    };
+                    let queue = app.state::<retry_queue::RetryQueue>();
+                    let queued = match disk_recording {
+                        Some(ref recording) => queue.enqueue_wav(recording.path(), meta),
+                        None => queue.enqueue_pcm16(&audio_data, audio::TARGET_SAMPLE_RATE, meta),
+                    };
+                    match queued {
+                        Ok(_) => {
+                            app.emit("retry-queue-updated", ()).ok();
+                            show_error(
+                                &app,
+                                "Transcription failed. Recording saved and will be retried automatically.",
+                            );
                         }
+                        Err(queue_err) => eprintln!("[RetryQueue] {}", queue_err),
                     }
-                } else {
-                    // No API key for selected provider - show error and return raw transcript
-                    had_llm_error = true;
-                    show_error(&app, &format!("No {} API key - rules skipped. Raw transcription copied. Add key in Settings to use rules.", provider_name));
-                    transcript
                 }
-            } else {
-                transcript
+                return Err(failure.message());
             }
         }
-    } else {
-        transcript
     };
 
+    let transformation = transformation_from_store(&app, mode_override.as_deref());
+    let (raw_transcript, transformed) = process_dictation(
+        &app,
+        transcript,
+        &options.language,
+        transformation,
+        Feedback::Window,
+    )
+    .await;
+    let had_llm_error = transformed.llm_error.is_some();
+    let final_text = transformed.text;
+
     app.emit("processing-state", false).ok();
 
     // Don't collapse window if there was an LLM error - let show_error handle it
@@ -369,6 +570,17 @@ async fn stop_recording(app: AppHandle) -> Result<(), String> {
         collapse_floating_window(&app)?;
     }
 
+    if !raw_transcript.is_empty() {
+        if let Ok(mut last) = LAST_DICTATION.lock() {
+            *last = Some(LastDictation {
+                raw_transcript: raw_transcript.clone(),
+                engine: engine.display_name().to_string(),
+                language: options.language.clone(),
+                duration_seconds: recording_seconds,
+            });
+        }
+    }
+
     if !final_text.is_empty() {
         // Update statistics
         if let Ok(store) = app.store("settings.json") {
@@ -416,19 +628,534 @@ async fn stop_recording(app: AppHandle) -> Result<(), String> {
             .ok();
         }
 
-        copy_and_paste(app, final_text).await?;
+        let insertion = get_insertion_method(
+            &app,
+            transformed.applied_mode.as_deref(),
+            profile.as_ref().and_then(|p| p.insertion_method.as_deref()),
+        );
+        record_history(
+            &app,
+            history::NewEntry {
+                source: history::Source::Dictation,
+                source_name: None,
+                processed_text: (final_text != raw_transcript).then(|| final_text.clone()),
+                raw_text: raw_transcript,
+                mode_id: transformed.applied_mode,
+                rules_applied: transformed.rules_applied,
+                llm_provider: transformed.llm_provider,
+                engine: engine.display_name().to_string(),
+                language: options.language.clone(),
+                duration_seconds: recording_seconds,
+                segments: Vec::new(),
+                words: Vec::new(),
+            },
+        );
+
+        insert_text(app, final_text, insertion).await?;
     }
 
     Ok(())
 }
 
+/// Mode ids understood by `rerun_last_dictation` in addition to real mode ids
+const RAW_MODE_ID: &str = "none";
+const RULES_MODE_ID: &str = "rules";
+
+/// What to do with a transcript after speech-to-text
+enum Transformation {
+    /// Use the transcript as-is
+    Raw,
+    /// Rewrite with a mode's prompt (rules are ignored)
+    Mode(String),
+    /// Apply the enabled transcription rules, if any
+    Rules,
+}
+
+impl Transformation {
+    fn from_mode_id(mode_id: &str) -> Self {
+        match mode_id {
+            "" | RAW_MODE_ID => Self::Raw,
+            RULES_MODE_ID => Self::Rules,
+            id => Self::Mode(id.to_string()),
+        }
+    }
+
+    fn mode_id(&self) -> &str {
+        match self {
+            Self::Raw => RAW_MODE_ID,
+            Self::Rules => RULES_MODE_ID,
+            Self::Mode(id) => id,
+        }
+    }
+}
+
+/// The transformation selected in the floating window (modes take priority over rules).
+/// A mode shortcut's or app profile's mode replaces the selected one.
+fn transformation_from_store(app: &AppHandle, mode_override: Option<&str>) -> Transformation {
+    if should_skip_rules(app) {
+        println!("[Dictato] Transformation skipped for this recording");
+        Transformation::Raw
+    } else if let Some(mode_id) = mode_override {
+        Transformation::from_mode_id(mode_id)
+    } else if let Some(mode_id) = get_active_mode_from_store(app) {
+        Transformation::Mode(mode_id)
+    } else {
+        Transformation::Rules
+    }
+}
+
+/// Result of running a transcript through a mode or rules
+struct Transformed {
+    text: String,
+    applied_mode: Option<String>,
+    rules_applied: bool,
+    /// LLM provider and model that produced the text, when one was used
+    llm_provider: Option<String>,
+    /// Why the mode or rules couldn't be applied. With `Feedback::Window` it
+    /// is also shown, and the floating window must stay open for it.
+    llm_error: Option<String>,
+}
+
+impl Transformed {
+    fn unchanged(text: String) -> Self {
+        Self {
+            text,
+            applied_mode: None,
+            rules_applied: false,
+            llm_provider: None,
+            llm_error: None,
+        }
+    }
+
+    fn llm_failed(text: String, error: String) -> Self {
+        Self {
+            llm_error: Some(error),
+            ..Self::unchanged(text)
+        }
+    }
+}
+
+/// Where post-processing reports its progress and errors
+#[derive(Clone, Copy, PartialEq)]
+enum Feedback {
+    /// A dictation the user is waiting for: progress, streamed text and errors
+    /// go to the floating window
+    Window,
+    /// Unattended work such as retry-queue entries: nothing is emitted, so a
+    /// recording started meanwhile keeps the window to itself. Errors are only
+    /// returned.
+    Quiet,
+}
+
+/// Post-process a dictated transcript: spoken formatting commands, then the
+/// mode or rules (modes take priority over rules). Returns the transcript
+/// after the commands, which is what history and re-runs treat as raw.
+async fn process_dictation(
+    app: &AppHandle,
+    transcript: String,
+    language: &str,
+    transformation: Transformation,
+    feedback: Feedback,
+) -> (String, Transformed) {
+    // Spoken formatting commands ("new line", "comma", "scratch that")
+    let transcript = if !transcript.is_empty() && is_voice_commands_enabled(app) {
+        voice_commands::apply(&transcript, &get_voice_commands(app, language))
+    } else {
+        transcript
+    };
+
+    let transformed = if transcript.is_empty() {
+        Transformed::unchanged(transcript.clone())
+    } else {
+        apply_transformation(app, transformation, transcript.clone(), feedback).await
+    };
+    (transcript, transformed)
+}
+
+/// Apply a mode or rules with the selected LLM provider, falling back along the
+/// configured chain. On failure the raw transcript is returned so nothing is
+/// lost, with the error (shown too, for `Feedback::Window`).
+async fn apply_transformation(
+    app: &AppHandle,
+    transformation: Transformation,
+    transcript: String,
+    feedback: Feedback,
+) -> Transformed {
+    // A misconfigured provider skips the LLM step instead of quietly sending
+    // the transcript somewhere the user didn't choose
+    let (llm_targets, llm_config_error) = match get_llm_targets(app) {
+        Ok(targets) => (targets, None),
+        Err(e) => (Vec::new(), Some(e)),
+    };
+    let provider_name = get_llm_provider_from_store(app)
+        .map(|provider| get_llm_provider_name(&provider))
+        .unwrap_or("LLM");
+    let live = feedback == Feedback::Window;
+    // Stream LLM output into the floating window as it is generated
+    let emit_partial = |text: &str| {
+        app.emit("processing-partial", text).ok();
+    };
+    let on_partial = live.then_some(&emit_partial as llm::PartialCallback<'_>);
+    let emit_message = |message: &str| {
+        if live {
+            app.emit("processing-message", message).ok();
+        }
+    };
+    let describe = |index: Option<usize>| {
+        if live {
+            describe_llm_target(app, &llm_targets, index)
+        } else {
+            llm_target_description(&llm_targets, index)
+        }
+    };
+    let fail = |transcript: String, error: String| {
+        if live {
+            show_error(app, &error);
+        }
+        Transformed::llm_failed(transcript, error)
+    };
+
+    // Local find/replace rules run before any LLM step, so they work offline
+    let transcript = match transformation {
+        Transformation::Raw => transcript,
+        _ => apply_local_rules(app, transcript),
+    };
+
+    match transformation {
+        Transformation::Raw => Transformed::unchanged(transcript),
+        Transformation::Mode(mode_id) => {
+            // Mode is active - get prompt and apply transformation (rules are ignored)
+            if let Some(prompt) = get_mode_prompt_from_store(app, &mode_id) {
+                // Check for LLM API key
+                if !llm_targets.is_empty() {
+                    emit_message("Applying mode...");
+                    match llm::process_with_prompt(&llm_targets, &transcript, &prompt, on_partial).await {
+                        Ok(outcome) => {
+                            let llm_used = describe(outcome.target_index);
+                            println!("[Dictato] Mode '{}' applied successfully using {}", mode_id, llm_used.as_deref().unwrap_or(provider_name));
+                            Transformed {
+                                applied_mode: Some(mode_id),
+                                llm_provider: llm_used,
+                                ..Transformed::unchanged(outcome.text)
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("[Dictato] Mode processing failed, using raw transcript: {}", e);
+                            fail(transcript, format_llm_error(&e))
+                        }
+                    }
+                } else {
+                    // No usable provider or API key - report it and return raw transcript
+                    fail(transcript, llm_config_error.unwrap_or_else(|| {
+                        format!("No {} API key - mode skipped. Raw transcription copied. Add key in Settings to use modes.", provider_name)
+                    }))
+                }
+            } else {
+                println!("[Dictato] Mode '{}' not found, using raw transcript", mode_id);
+                Transformed::unchanged(transcript)
+            }
+        }
+        Transformation::Rules => {
+            // No mode active - apply rules if any are enabled
+            let rules = get_transcription_rules_from_store(app);
+            let has_enabled_rules = rules.iter().any(|r| r.enabled);
+            if !has_enabled_rules {
+                return Transformed::unchanged(transcript);
+            }
+            // Check for LLM API key
+            if !llm_targets.is_empty() {
+                emit_message("Applying rules...");
+                match llm::process_with_rules(&llm_targets, &transcript, rules, on_partial).await {
+                    Ok(outcome) => {
+                        let llm_used = describe(outcome.target_index);
+                        println!("[Dictato] Rules applied successfully using {}", llm_used.as_deref().unwrap_or(provider_name));
+                        Transformed {
+                            rules_applied: true,
+                            llm_provider: llm_used,
+                            ..Transformed::unchanged(outcome.text)
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("[Dictato] Rule processing failed, using raw transcript: {}", e);
+                        fail(transcript, format_llm_error(&e))
+                    }
+                }
+            } else {
+                // No usable provider or API key - report it and return raw transcript
+                fail(transcript, llm_config_error.unwrap_or_else(|| {
+                    format!("No {} API key - rules skipped. Raw transcription copied. Add key in Settings to use rules.", provider_name)
+                }))
+            }
+        }
+    }
+}
+
+fn apply_local_rules(app: &AppHandle, transcript: String) -> String {
+    let rules = get_local_rules_from_store(app);
+    if !rules.iter().any(|r| r.enabled) {
+        return transcript;
+    }
+    let (text, changed) = local_rules::apply(&transcript, &rules);
+    if changed > 0 {
+        println!("[Dictato] {} local rule(s) applied", changed);
+    }
+    text
+}
+
+/// Re-process the last dictation without recording again and paste the result.
+/// `mode_id` is a mode id, "rules" for the enabled rules or "none" for the raw
+/// transcript; when omitted the current selection in settings is used.
+#[tauri::command]
+async fn rerun_last_dictation(app: AppHandle, mode_id: Option<String>) -> Result<(), String> {
+    if IS_RECORDING.load(Ordering::SeqCst) {
+        return Err("Finish the current recording before re-running".to_string());
+    }
+    // A second press of the re-run shortcut would paste the result twice
+    if IS_RERUNNING.swap(true, Ordering::SeqCst) {
+        return Err("The last dictation is already being re-run".to_string());
+    }
+    let result = rerun_dictation(app, mode_id).await;
+    IS_RERUNNING.store(false, Ordering::SeqCst);
+    result
+}
+
+async fn rerun_dictation(app: AppHandle, mode_id: Option<String>) -> Result<(), String> {
+    let last = LAST_DICTATION
+        .lock()
+        .ok()
+        .and_then(|guard| guard.clone())
+        .ok_or_else(|| "No dictation to re-run yet".to_string())?;
+
+    let transformation = match mode_id {
+        Some(ref id) => Transformation::from_mode_id(id),
+        None => transformation_from_store(&app, None),
+    };
+
+    println!(
+        "[Dictato] Re-running last dictation ({} chars) with mode {:?}",
+        last.raw_transcript.len(),
+        mode_id
+    );
+
+    expand_floating_window(&app)?;
+    app.emit("processing-state", true).ok();
+    let transformed = apply_transformation(
+        &app,
+        transformation,
+        last.raw_transcript.clone(),
+        Feedback::Window,
+    )
+    .await;
+    app.emit("processing-state", false).ok();
+
+    if transformed.llm_error.is_none() {
+        collapse_floating_window(&app)?;
+    }
+
+    let insertion = get_insertion_method(&app, transformed.applied_mode.as_deref(), None);
+    record_history(
+        &app,
+        history::NewEntry {
+            source: history::Source::Dictation,
+            source_name: None,
+            processed_text: (transformed.text != last.raw_transcript).then(|| transformed.text.clone()),
+            raw_text: last.raw_transcript,
+            mode_id: transformed.applied_mode,
+            rules_applied: transformed.rules_applied,
+            llm_provider: transformed.llm_provider,
+            engine: last.engine,
+            language: last.language,
+            duration_seconds: last.duration_seconds,
+            segments: Vec::new(),
+            words: Vec::new(),
+        },
+    );
+
+    insert_text(app, transformed.text, insertion).await
+}
+
+/// Persist a finished transcription. Failures are logged, never surfaced,
+/// so a broken history database can't block pasting.
+fn record_history(app: &AppHandle, entry: history::NewEntry) {
+    if let Err(e) = app.state::<history::HistoryState>().insert(entry) {
+        eprintln!("[History] Failed to record transcription: {}", e);
+    }
+}
+
+/// Every engine's error from a failed `transcribe_with_fallback`, in chain order
+struct SttFailure {
+    errors: Vec<(std::sync::Arc<dyn stt::SttEngine>, String)>,
+}
+
+impl SttFailure {
+    /// The error to report: the first engine's
+    fn message(&self) -> String {
+        self.errors
+            .first()
+            .map(|(_, e)| e.clone())
+            .unwrap_or_else(|| "No STT engine available".to_string())
+    }
+
+    /// A remote engine that failed in a way retrying later may fix
+    fn retryable(&self) -> Option<&(std::sync::Arc<dyn stt::SttEngine>, String)> {
+        self.errors
+            .iter()
+            .find(|(engine, e)| {
+                engine.provider().is_remote() && openai_stt::is_transient_error(e)
+            })
+    }
+}
+
+/// Try each engine in turn on the same audio until one succeeds. Returns the
+/// text with the engine that produced it, or every engine's error.
+async fn transcribe_with_fallback(
+    app: &AppHandle,
+    engines: &[std::sync::Arc<dyn stt::SttEngine>],
+    audio_data: &[u8],
+    disk_recording: Option<&groq::DiskRecording>,
+    options: &stt::TranscribeOptions,
+) -> Result<(String, std::sync::Arc<dyn stt::SttEngine>), SttFailure> {
+    if let Some(recording) = disk_recording {
+        return transcribe_disk_recording(
+            app,
+            engines,
+            recording.path(),
+            options,
+            Feedback::Window,
+        )
+        .await;
+    }
+
+    let mut errors = Vec::new();
+    for (i, engine) in engines.iter().enumerate() {
+        if i > 0 {
+            announce_fallback(app, engine.as_ref());
+        }
+        println!(
+            "[Dictato] Transcribing {} bytes with {}",
+            audio_data.len(),
+            engine.display_name()
+        );
+        match engine.transcribe_pcm16(app, audio_data.to_vec(), options).await {
+            Ok(text) => {
+                if i > 0 {
+                    app.emit("stt-engine-used", engine.display_name()).ok();
+                }
+                return Ok((text, engine.clone()));
+            }
+            Err(e) => {
+                eprintln!("[Dictato] {} transcription failed: {}", engine.display_name(), e);
+                errors.push((engine.clone(), e));
+            }
+        }
+    }
+    Err(SttFailure { errors })
+}
+
+fn announce_fallback(app: &AppHandle, engine: &dyn stt::SttEngine) {
+    println!("[Dictato] Falling back to {}", engine.display_name());
+    app.emit(
+        "processing-message",
+        format!("Retrying with {}...", engine.display_name()),
+    )
+    .ok();
+}
+
+/// Transcribe a recording WAV (long recording or retry queue entry) one piece
+/// at a time so memory stays bounded regardless of how long the user dictated.
+/// Pieces are cut in pauses, like long files, so no word is split in two.
+/// A piece that fails moves on to the next engine, which then transcribes
+/// the rest; pieces already transcribed are kept. Returns the engine that
+/// transcribed the last piece. Progress and fallbacks are only announced
+/// with `Feedback::Window`.
+async fn transcribe_disk_recording(
+    app: &AppHandle,
+    engines: &[std::sync::Arc<dyn stt::SttEngine>],
+    wav_path: &std::path::Path,
+    options: &stt::TranscribeOptions,
+    feedback: Feedback,
+) -> Result<(String, std::sync::Arc<dyn stt::SttEngine>), SttFailure> {
+    let Some(first) = engines.first() else {
+        return Err(SttFailure { errors: Vec::new() });
+    };
+    // Reading the WAV isn't an engine's fault, but is reported like one
+    let read_failure = |e: String| SttFailure {
+        errors: vec![(first.clone(), e)],
+    };
+    let total_chunks = decode::probe_duration(wav_path)
+        .map(|duration| chunking::window_count(duration, LONG_RECORDING_CHUNK_SECONDS))
+        .unwrap_or(1);
+    let mut reader = decode::WindowReader::open(
+        wav_path,
+        audio::TARGET_SAMPLE_RATE,
+        LONG_RECORDING_CHUNK_SECONDS,
+        chunking::WINDOW_OVERLAP_SECS,
+        chunking::PAUSE_SEARCH_SECS,
+    )
+    .map_err(read_failure)?;
+    println!(
+        "[Dictato] Transcribing disk recording in ~{} chunk(s) with {}",
+        total_chunks,
+        first.display_name()
+    );
+
+    let mut stitcher = chunking::Stitcher::default();
+    let mut errors = Vec::new();
+    let mut current = 0;
+    let mut index = 0;
+    while let Some(window) = reader.next_window().map_err(read_failure)? {
+        if total_chunks > 1 && feedback == Feedback::Window {
+            app.emit(
+                "processing-message",
+                format!("Transcribing part {}/{}...", index + 1, total_chunks.max(index + 1)),
+            )
+            .ok();
+        }
+        let pcm16 = wav::f32_to_pcm16(&window.samples);
+        let text = loop {
+            let engine = &engines[current];
+            match engine.transcribe_pcm16(app, pcm16.clone(), options).await {
+                Ok(text) => break text,
+                Err(e) => {
+                    eprintln!(
+                        "[Dictato] {} failed on chunk {}: {}",
+                        engine.display_name(),
+                        index + 1,
+                        e
+                    );
+                    errors.push((engine.clone(), e));
+                    current += 1;
+                    match engines.get(current) {
+                        Some(next) if feedback == Feedback::Window => {
+                            announce_fallback(app, next.as_ref())
+                        }
+                        Some(_) => {}
+                        None => return Err(SttFailure { errors }),
+                    }
+                }
+            }
+        };
+        let transcript = stt::TimedTranscript {
+            text: text.trim().to_string(),
+            ..Default::default()
+        };
+        stitcher.push(transcript, window.start_secs, window.overlap_secs);
+        index += 1;
+    }
+
+    let engine = engines[current].clone();
+    if current > 0 && feedback == Feedback::Window {
+        app.emit("stt-engine-used", engine.display_name()).ok();
+    }
+    Ok((stitcher.finish().text, engine))
+}
+
 #[tauri::command]
 async fn cancel_recording(app: AppHandle) -> Result<(), String> {
-    if !IS_RECORDING.load(Ordering::SeqCst) {
+    if !IS_RECORDING.swap(false, Ordering::SeqCst) {
         return Ok(());
     }
 
-    IS_RECORDING.store(false, Ordering::SeqCst);
     app.emit("recording-state", false).ok();
 
     // Unregister cancel shortcut since recording stopped
@@ -577,7 +1304,8 @@ fn open_accessibility_settings() -> Result<(), String> {
 
 /// Execute paste via the platform-appropriate method with fallback.
 /// `context` is a label for log messages (e.g. "Auto-paste", "Pure paste").
-async fn execute_paste(context: &str) {
+/// Returns whether the paste keystroke was sent.
+async fn execute_paste(context: &str) -> bool {
     let ctx = context.to_string();
     let paste_result = tokio::task::spawn_blocking(move || {
         #[cfg(target_os = "macos")]
@@ -620,24 +1348,33 @@ async fn execute_paste(context: &str) {
     .await;
 
     match paste_result {
-        Ok(Ok(())) => {}
+        Ok(Ok(())) => true,
         Ok(Err(e)) => {
             println!(
                 "[Dictato] {} failed: {}. Text is in clipboard - press Cmd+V to paste.",
                 context, e
             );
+            false
         }
         Err(e) => {
             println!(
                 "[Dictato] {} task failed: {:?}. Text is in clipboard - press Cmd+V to paste.",
                 context, e
             );
+            false
         }
     }
 }
 
 #[tauri::command]
 async fn copy_and_paste(app: AppHandle, text: String) -> Result<(), String> {
+    let auto_paste_enabled = is_auto_paste_enabled(&app);
+
+    // Without auto-paste the clipboard is how the text is delivered, so only
+    // snapshot it when pasting
+    let snapshot = (auto_paste_enabled && is_restore_clipboard_enabled(&app))
+        .then(|| clipboard::ClipboardSnapshot::capture(&app));
+
     // Always copy to clipboard first
     app.clipboard()
         .write_text(&text)
@@ -645,11 +1382,6 @@ async fn copy_and_paste(app: AppHandle, text: String) -> Result<(), String> {
 
     println!("[Dictato] Text copied to clipboard");
 
-    // Check if auto-paste is enabled
-    let auto_paste_enabled = get_store_string(&app, store_keys::AUTO_PASTE)
-        .map(|v| v == "true")
-        .unwrap_or(true); // Default to enabled
-
     if !auto_paste_enabled {
         println!("[Dictato] Auto-paste disabled. Press Cmd+V to paste.");
         return Ok(());
@@ -666,11 +1398,57 @@ async fn copy_and_paste(app: AppHandle, text: String) -> Result<(), String> {
     // Small delay to let clipboard propagate through the pasteboard system
     tokio::time::sleep(std::time::Duration::from_millis(CLIPBOARD_PROPAGATION_DELAY_MS)).await;
 
-    execute_paste("Auto-paste").await;
+    let pasted = execute_paste("Auto-paste").await;
+
+    // Leave the text in the clipboard if the paste failed so the user can paste manually
+    if let Some(snapshot) = snapshot.filter(|_| pasted) {
+        let delay_ms = get_clipboard_restore_delay_ms(&app);
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+            if let Err(e) = snapshot.restore(&app, &text) {
+                println!("[Dictato] {}", e);
+            }
+        });
+    }
 
     Ok(())
 }
 
+/// Deliver dictated text to the focused app with the chosen method.
+/// Typing falls back to clipboard paste if it fails, so the text is never lost.
+async fn insert_text(app: AppHandle, text: String, method: typing::InsertionMethod) -> Result<(), String> {
+    if method == typing::InsertionMethod::Paste || !is_auto_paste_enabled(&app) {
+        return copy_and_paste(app, text).await;
+    }
+
+    let char_count = text.chars().count();
+    let to_type = text.clone();
+    let result = tokio::task::spawn_blocking(move || typing::type_text(&to_type))
+        .await
+        .map_err(|e| format!("Typing task failed: {:?}", e))?;
+
+    match result {
+        Ok(()) => {
+            println!("[Dictato] Typed {} characters", char_count);
+            Ok(())
+        }
+        Err(e) if e.typed_len == 0 => {
+            println!("[Dictato] Typing failed: {}. Falling back to paste.", e.message);
+            copy_and_paste(app, text).await
+        }
+        Err(e) => {
+            // Pasting everything would repeat what already reached the app
+            println!(
+                "[Dictato] Typing failed after {} of {} characters: {}. Pasting the rest.",
+                text[..e.typed_len].chars().count(),
+                char_count,
+                e.message
+            );
+            copy_and_paste(app, text[e.typed_len..].to_string()).await
+        }
+    }
+}
+
 #[tauri::command]
 fn unregister_shortcuts(app: AppHandle) -> Result<(), String> {
     app.global_shortcut()
@@ -684,63 +1462,117 @@ async fn register_shortcut(app: AppHandle, shortcut_str: String) -> Result<(), S
 
     // Unregister all shortcuts first to avoid duplicates
     app.global_shortcut().unregister_all().ok();
+    // A shortcut held while being replaced never reports its release
+    if let Ok(mut held) = HELD_SHORTCUTS.lock() {
+        held.clear();
+    }
 
     let app_clone = app.clone();
     app.global_shortcut()
-        .on_shortcut(shortcut, move |_app, _shortcut, event| {
-            if event.state != ShortcutState::Pressed {
-                return;
-            }
-            let app = app_clone.clone();
-            tauri::async_runtime::spawn(async move {
-                if IS_RECORDING.load(Ordering::SeqCst) {
-                    if let Err(e) = stop_recording(app).await {
-                        eprintln!("Failed to stop recording: {}", e);
-                    }
-                } else {
-                    let stt_provider = get_stt_provider_from_store(&app);
-                    let can_record = match stt_provider {
-                        parakeet::SttProvider::Parakeet => {
-                            let parakeet_state = app.state::<parakeet::ParakeetState>();
-                            parakeet::is_model_loaded(&parakeet_state)
-                        }
-                        parakeet::SttProvider::Whisper => {
-                            let whisper_state = app.state::<whisper::WhisperState>();
-                            whisper::is_model_loaded(&whisper_state)
-                        }
-                        parakeet::SttProvider::Groq => {
-                            get_groq_api_key_from_store(&app).is_some()
-                        }
-                    };
-
-                    if can_record {
-                        if let Err(e) = start_recording(app).await {
-                            eprintln!("Failed to start recording: {}", e);
-                        }
-                    } else {
-                        match stt_provider {
-                            parakeet::SttProvider::Parakeet => {
-                                show_error(&app, "Parakeet model not loaded. Download it in Settings.");
-                            }
-                            parakeet::SttProvider::Whisper => {
-                                show_error(&app, "Whisper model not loaded. Download it in Settings.");
-                            }
-                            parakeet::SttProvider::Groq => {
-                                show_error(&app, "No API key configured. Add your Groq API key in Settings.");
-                            }
-                        }
-                    }
-                }
-            });
+        .on_shortcut(shortcut, move |_app, shortcut, event| {
+            handle_recording_shortcut(app_clone.clone(), shortcut, event.state, None);
         })
         .map_err(|e| e.to_string())?;
 
-    // Also re-register the pure paste shortcut (since unregister_all() cleared it)
+    // Also re-register the other shortcuts (since unregister_all() cleared them)
     register_pure_paste_shortcut_internal(&app).ok();
+    register_rerun_shortcut_internal(&app).ok();
+    register_mode_shortcuts_internal(&app).ok();
 
     Ok(())
 }
 
+/// Toggle or hold-to-talk handling shared by the main shortcut and the mode
+/// shortcuts. `mode_id` is the mode a recording started from this shortcut uses.
+/// In hold-to-talk mode the recording ends once no recording shortcut is held,
+/// so releasing a second shortcut pressed mid-recording doesn't cut it short.
+fn handle_recording_shortcut(
+    app: AppHandle,
+    shortcut: &Shortcut,
+    state: ShortcutState,
+    mode_id: Option<String>,
+) {
+    if is_hold_to_talk_enabled(&app) {
+        let pressed = state == ShortcutState::Pressed;
+        let Ok(mut held) = HELD_SHORTCUTS.lock() else {
+            return;
+        };
+        // Key repeat delivers repeated presses while held; only react to edges
+        let changed = if pressed {
+            held.insert(shortcut.id())
+        } else {
+            held.remove(&shortcut.id())
+        };
+        if !changed || (!pressed && !held.is_empty()) {
+            return;
+        }
+        drop(held);
+        tauri::async_runtime::spawn(async move {
+            let _guard = HOLD_TO_TALK_LOCK.lock().await;
+            if !pressed {
+                finish_held_recording(app).await;
+            } else if !IS_RECORDING.load(Ordering::SeqCst) {
+                start_recording_if_ready(app, mode_id).await;
+            }
+        });
+        return;
+    }
+
+    if state != ShortcutState::Pressed {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        if IS_RECORDING.load(Ordering::SeqCst) {
+            if let Err(e) = stop_recording(app).await {
+                eprintln!("Failed to stop recording: {}", e);
+            }
+        } else {
+            start_recording_if_ready(app, mode_id).await;
+        }
+    });
+}
+
+/// Start recording from the shortcut, or tell the user why the engine can't run.
+/// The focused app's profile may pick a different engine than the global
+/// setting, so it is detected first and its engine is the one checked. A local
+/// engine only needs its model downloaded; it is loaded on first use.
+async fn start_recording_if_ready(app: AppHandle, mode_id: Option<String>) {
+    let profile = detect_app_profile(&app);
+    let ready = get_stt_engine_for_profile(&app, profile.as_ref())
+        .and_then(|engine| engine.check_ready(&app));
+    match ready {
+        Ok(()) => {
+            if let Err(e) = start_recording_with_profile(app, mode_id, profile).await {
+                eprintln!("Failed to start recording: {}", e);
+            }
+        }
+        Err(e) => show_error(&app, &e),
+    }
+}
+
+/// Hold-to-talk release: transcribe, or discard the recording if the
+/// shortcut was only tapped
+async fn finish_held_recording(app: AppHandle) {
+    if !IS_RECORDING.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let held_ms = RECORDING_STARTED_AT
+        .lock()
+        .ok()
+        .and_then(|started_at| started_at.map(|t| t.elapsed().as_millis()))
+        .unwrap_or(0);
+
+    if held_ms < MIN_HOLD_DURATION_MS {
+        println!("[Dictato] Shortcut held for {}ms, discarding recording", held_ms);
+        if let Err(e) = cancel_recording(app).await {
+            eprintln!("Failed to cancel recording: {}", e);
+        }
+    } else if let Err(e) = stop_recording(app).await {
+        eprintln!("Failed to stop recording: {}", e);
+    }
+}
+
 fn register_cancel_shortcut_internal(app: &AppHandle, shortcut_str: &str) -> Result<(), String> {
     let shortcut: Shortcut = shortcut_str.parse().map_err(|e| format!("{:?}", e))?;
 
@@ -809,65 +1641,410 @@ fn get_anthropic_api_key_from_store(app: &AppHandle) -> Option<String> {
     get_store_string(app, store_keys::ANTHROPIC_API_KEY)
 }
 
-fn get_llm_provider_from_store(app: &AppHandle) -> llm::LlmProvider {
-    get_store_string(app, store_keys::LLM_PROVIDER)
-        .and_then(|s| match s.as_str() {
-            "openai" => Some(llm::LlmProvider::OpenAI),
-            "google" => Some(llm::LlmProvider::Google),
-            "anthropic" => Some(llm::LlmProvider::Anthropic),
-            _ => None,
-        })
-        .unwrap_or_default()
+/// The selected LLM provider, OpenAI if none was chosen. A selection that
+/// can't be used is an error, never a switch to another provider.
+fn get_llm_provider_from_store(app: &AppHandle) -> Result<llm::LlmProvider, String> {
+    match get_store_string(app, store_keys::LLM_PROVIDER) {
+        Some(value) => parse_llm_provider(app, &value),
+        None => Ok(llm::LlmProvider::default()),
+    }
 }
 
-/// Get the API key for the currently selected LLM provider
+/// Map a provider id from settings to a provider. "custom" requires a base URL.
+fn parse_llm_provider(app: &AppHandle, value: &str) -> Result<llm::LlmProvider, String> {
+    match value {
+        "openai" => Ok(llm::LlmProvider::OpenAI),
+        "google" => Ok(llm::LlmProvider::Google),
+        "anthropic" => Ok(llm::LlmProvider::Anthropic),
+        "custom" => get_store_string(app, store_keys::CUSTOM_LLM_BASE_URL)
+            .filter(|url| !url.trim().is_empty())
+            .map(|base_url| llm::LlmProvider::Custom { base_url })
+            .ok_or_else(|| "Custom LLM endpoint has no base URL. Set it in Settings.".to_string()),
+        other => Err(format!("Unknown LLM provider: {}", other)),
+    }
+}
+
+/// Get the API key for the currently selected LLM provider.
+/// Custom endpoints may run without a key, so they always yield one (possibly empty).
 fn get_llm_api_key_for_provider(app: &AppHandle, provider: &llm::LlmProvider) -> Option<String> {
     match provider {
         llm::LlmProvider::OpenAI => get_openai_api_key_from_store(app),
         llm::LlmProvider::Google => get_google_api_key_from_store(app),
         llm::LlmProvider::Anthropic => get_anthropic_api_key_from_store(app),
+        llm::LlmProvider::Custom { .. } => {
+            Some(get_store_string(app, store_keys::CUSTOM_LLM_API_KEY).unwrap_or_default())
+        }
+    }
+}
+
+/// Get the user-selected model for a provider, falling back to the provider default
+fn get_llm_model_for_provider(app: &AppHandle, provider: &llm::LlmProvider) -> String {
+    let key = match provider {
+        llm::LlmProvider::OpenAI => store_keys::OPENAI_MODEL,
+        llm::LlmProvider::Google => store_keys::GOOGLE_MODEL,
+        llm::LlmProvider::Anthropic => store_keys::ANTHROPIC_MODEL,
+        llm::LlmProvider::Custom { .. } => store_keys::CUSTOM_LLM_MODEL,
+    };
+    get_store_string(app, key)
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| llm::default_model(provider).to_string())
+}
+
+/// The selected provider followed by the configured fallbacks. Providers
+/// without an API key are left out; the same provider can appear again with a
+/// different model. Fails if the selected provider itself is misconfigured.
+fn get_llm_targets(app: &AppHandle) -> Result<Vec<llm::LlmTarget>, String> {
+    #[derive(serde::Deserialize)]
+    struct FallbackEntry {
+        provider: String,
+        #[serde(default)]
+        model: Option<String>,
+    }
+
+    let primary = get_llm_provider_from_store(app)?;
+    let primary_model = get_llm_model_for_provider(app, &primary);
+    let mut candidates = vec![(primary, primary_model)];
+
+    let fallbacks: Vec<FallbackEntry> = get_store_string(app, store_keys::LLM_FALLBACK_CHAIN)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    for entry in fallbacks {
+        match parse_llm_provider(app, &entry.provider) {
+            Ok(provider) => {
+                let model = entry
+                    .model
+                    .filter(|m| !m.trim().is_empty())
+                    .unwrap_or_else(|| get_llm_model_for_provider(app, &provider));
+                candidates.push((provider, model));
+            }
+            Err(e) => println!("[Dictato] Skipping LLM fallback '{}': {}", entry.provider, e),
+        }
+    }
+
+    let mut targets: Vec<llm::LlmTarget> = Vec::new();
+    for (provider, model) in candidates {
+        if targets.iter().any(|t| t.provider == provider && t.model == model) {
+            continue;
+        }
+        if let Some(api_key) = get_llm_api_key_for_provider(app, &provider) {
+            targets.push(llm::LlmTarget {
+                provider,
+                api_key,
+                model,
+            });
+        }
+    }
+    Ok(targets)
+}
+
+/// "Provider / model" of the target that produced an LLM result
+fn llm_target_description(targets: &[llm::LlmTarget], index: Option<usize>) -> Option<String> {
+    let target = targets.get(index?)?;
+    Some(format!(
+        "{} / {}",
+        get_llm_provider_name(&target.provider),
+        target.model
+    ))
+}
+
+/// Like `llm_target_description`, but emits "llm-provider-used" when a
+/// fallback had to step in
+fn describe_llm_target(
+    app: &AppHandle,
+    targets: &[llm::LlmTarget],
+    index: Option<usize>,
+) -> Option<String> {
+    let description = llm_target_description(targets, index)?;
+    if index.is_some_and(|i| i > 0) {
+        println!("[Dictato] LLM fallback used: {}", description);
+        app.emit("llm-provider-used", &description).ok();
+    }
+    Some(description)
+}
+
+/// Get the display name for an LLM provider
+fn get_llm_provider_name(provider: &llm::LlmProvider) -> &'static str {
+    match provider {
+        llm::LlmProvider::OpenAI => "OpenAI",
+        llm::LlmProvider::Google => "Google",
+        llm::LlmProvider::Anthropic => "Anthropic",
+        llm::LlmProvider::Custom { .. } => "Custom endpoint",
     }
 }
 
-/// Get the user-selected model for a provider, falling back to the provider default
-fn get_llm_model_for_provider(app: &AppHandle, provider: &llm::LlmProvider) -> String {
-    let key = match provider {
-        llm::LlmProvider::OpenAI => store_keys::OPENAI_MODEL,
-        llm::LlmProvider::Google => store_keys::GOOGLE_MODEL,
-        llm::LlmProvider::Anthropic => store_keys::ANTHROPIC_MODEL,
-    };
-    get_store_string(app, key)
-        .filter(|m| !m.trim().is_empty())
-        .unwrap_or_else(|| llm::default_model(provider).to_string())
+fn get_stt_provider_from_store(app: &AppHandle) -> stt::SttProvider {
+    get_store_string(app, store_keys::STT_PROVIDER)
+        .map(|s| stt::SttProvider::from_store_value(&s))
+        .unwrap_or(stt::SttProvider::Groq)
+}
+
+/// Providers in the configured fallback chain, skipping unknown values
+fn get_stt_fallback_providers(app: &AppHandle) -> Vec<stt::SttProvider> {
+    let fallbacks: Vec<String> = get_store_string(app, store_keys::STT_FALLBACK_CHAIN)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    fallbacks
+        .into_iter()
+        .filter_map(|value| {
+            let provider = stt::SttProvider::parse(&value);
+            if provider.is_none() {
+                println!("[Dictato] Skipping unknown STT fallback '{}'", value);
+            }
+            provider
+        })
+        .collect()
+}
+
+/// `primary` followed by the engines in the configured fallback chain,
+/// without duplicates
+fn get_stt_engine_chain(
+    app: &AppHandle,
+    primary: std::sync::Arc<dyn stt::SttEngine>,
+) -> Vec<std::sync::Arc<dyn stt::SttEngine>> {
+    let registry = app.state::<stt::SttRegistry>();
+    let mut chain = vec![primary];
+    for provider in get_stt_fallback_providers(app) {
+        if chain.iter().any(|e| e.provider() == provider) {
+            continue;
+        }
+        if let Ok(engine) = registry.get(&provider) {
+            chain.push(engine);
+        }
+    }
+    chain
+}
+
+/// Whether the model of a local provider is on disk. Remote providers have none.
+fn is_local_model_downloaded(app: &AppHandle, provider: &stt::SttProvider) -> Result<bool, String> {
+    match provider {
+        stt::SttProvider::Parakeet => {
+            parakeet::get_model_dir(app).map(|dir| parakeet::is_model_downloaded(&dir))
+        }
+        stt::SttProvider::Whisper => {
+            whisper::get_model_dir(app).map(|dir| whisper::is_model_downloaded(&dir))
+        }
+        stt::SttProvider::Groq | stt::SttProvider::Custom => Ok(false),
+    }
+}
+
+/// Load the models of local providers one after another in the background.
+/// Uses std::thread::spawn because setup() is not async.
+fn spawn_local_model_loads(app: &AppHandle, providers: Vec<stt::SttProvider>) {
+    if providers.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for provider in providers {
+            let result = match provider {
+                stt::SttProvider::Parakeet => parakeet::ensure_model_loaded(
+                    &app,
+                    app.state::<parakeet::ParakeetState>().inner(),
+                ),
+                stt::SttProvider::Whisper => {
+                    whisper::ensure_model_loaded(&app, app.state::<whisper::WhisperState>().inner())
+                }
+                stt::SttProvider::Groq | stt::SttProvider::Custom => Ok(()),
+            };
+            if let Err(e) = result {
+                eprintln!(
+                    "[Dictato] Failed to load the '{}' model on startup: {}",
+                    provider.as_store_value(),
+                    e
+                );
+            }
+        }
+    });
+}
+
+/// Look up the engine for the STT provider selected in settings
+fn get_stt_engine(app: &AppHandle) -> Result<std::sync::Arc<dyn stt::SttEngine>, String> {
+    let provider = get_stt_provider_from_store(app);
+    app.state::<stt::SttRegistry>().get(&provider)
+}
+
+/// The engine a dictation uses: the app profile's, if it sets one, otherwise
+/// the one selected in settings
+fn get_stt_engine_for_profile(
+    app: &AppHandle,
+    profile: Option<&app_profiles::AppProfile>,
+) -> Result<std::sync::Arc<dyn stt::SttEngine>, String> {
+    let Some(value) = profile.and_then(|p| p.stt_provider.as_deref()) else {
+        return get_stt_engine(app);
+    };
+    match stt::SttProvider::parse(value) {
+        Some(provider) => app.state::<stt::SttRegistry>().get(&provider),
+        None => {
+            println!("[Dictato] Ignoring unknown app profile STT provider '{}'", value);
+            get_stt_engine(app)
+        }
+    }
+}
+
+fn get_language_from_store(app: &AppHandle) -> String {
+    get_store_string(app, store_keys::LANGUAGE).unwrap_or_else(|| "en".to_string())
+}
+
+fn get_cancel_shortcut_from_store(app: &AppHandle) -> String {
+    get_store_string(app, store_keys::CANCEL_SHORTCUT).unwrap_or_else(|| "Escape".to_string())
+}
+
+fn get_transcription_rules_from_store(app: &AppHandle) -> Vec<llm::TranscriptionRule> {
+    get_store_string(app, store_keys::TRANSCRIPTION_RULES)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn get_local_rules_from_store(app: &AppHandle) -> Vec<local_rules::LocalRule> {
+    get_store_string(app, store_keys::LOCAL_RULES)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn get_dictionary_from_store(app: &AppHandle) -> Vec<dictionary::DictionaryEntry> {
+    get_store_string(app, store_keys::DICTIONARY)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn is_auto_paste_enabled(app: &AppHandle) -> bool {
+    get_store_string(app, store_keys::AUTO_PASTE)
+        .map(|v| v == "true")
+        .unwrap_or(true) // Default to enabled
+}
+
+fn get_app_profiles_from_store(app: &AppHandle) -> Vec<app_profiles::AppProfile> {
+    get_store_string(app, store_keys::APP_PROFILES)
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Profile matching the frontmost app, if any profiles are configured
+fn detect_app_profile(app: &AppHandle) -> Option<app_profiles::AppProfile> {
+    let profiles = get_app_profiles_from_store(app);
+    if !profiles.iter().any(|p| p.enabled) {
+        return None;
+    }
+    let frontmost = app_profiles::detect_frontmost_app()?;
+    match app_profiles::find_profile(&profiles, &frontmost) {
+        Some(profile) => {
+            println!(
+                "[Dictato] Using profile '{}' for {} ({})",
+                profile.name, frontmost.name, frontmost.id
+            );
+            app.emit("app-profile-applied", &profile.name).ok();
+            Some(profile.clone())
+        }
+        None => {
+            println!("[Dictato] No profile for {} ({})", frontmost.name, frontmost.id);
+            None
+        }
+    }
+}
+
+/// Opt-in: a sent paste keystroke doesn't mean the app accepted it, and when
+/// it didn't, the transcript left in the clipboard is the only copy
+fn is_restore_clipboard_enabled(app: &AppHandle) -> bool {
+    get_store_string(app, store_keys::RESTORE_CLIPBOARD)
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+fn get_clipboard_restore_delay_ms(app: &AppHandle) -> u64 {
+    get_store_string(app, store_keys::CLIPBOARD_RESTORE_DELAY_MS)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CLIPBOARD_RESTORE_DELAY_MS)
+}
+
+/// The app profile's insertion method, then the applied mode's, then the
+/// global setting. Defaults to paste.
+fn get_insertion_method(
+    app: &AppHandle,
+    applied_mode: Option<&str>,
+    profile_method: Option<&str>,
+) -> typing::InsertionMethod {
+    // An unrecognized value at one level falls through to the next
+    let parse = |v: String| typing::InsertionMethod::from_store_value(&v);
+    let mode_override = || {
+        applied_mode.and_then(|mode_id| {
+            get_store_string(app, store_keys::MODE_INSERTION_METHODS)
+                .and_then(|s| serde_json::from_str::<HashMap<String, String>>(&s).ok())
+                .and_then(|methods| methods.get(mode_id).cloned())
+                .and_then(parse)
+        })
+    };
+    profile_method
+        .and_then(typing::InsertionMethod::from_store_value)
+        .or_else(mode_override)
+        .or_else(|| get_store_string(app, store_keys::INSERTION_METHOD).and_then(parse))
+        .unwrap_or(typing::InsertionMethod::Paste)
+}
+
+fn is_voice_commands_enabled(app: &AppHandle) -> bool {
+    get_store_string(app, store_keys::VOICE_COMMANDS_ENABLED)
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
+
+/// User-defined commands followed by the built-in table for the language,
+/// so custom phrases override built-in ones
+fn get_voice_commands(app: &AppHandle, language: &str) -> Vec<voice_commands::VoiceCommand> {
+    let mut commands: Vec<voice_commands::VoiceCommand> =
+        get_store_string(app, store_keys::VOICE_COMMANDS)
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+    commands.extend(voice_commands::builtin_commands(language));
+    commands
 }
 
-/// Get the display name for an LLM provider
-fn get_llm_provider_name(provider: &llm::LlmProvider) -> &'static str {
-    match provider {
-        llm::LlmProvider::OpenAI => "OpenAI",
-        llm::LlmProvider::Google => "Google",
-        llm::LlmProvider::Anthropic => "Anthropic",
-    }
+/// Silence trimming is opt-in: it changes what every engine receives
+fn is_vad_enabled(app: &AppHandle) -> bool {
+    get_store_string(app, store_keys::VAD_ENABLED)
+        .map(|v| v == "true")
+        .unwrap_or(false)
 }
 
-fn get_stt_provider_from_store(app: &AppHandle) -> parakeet::SttProvider {
-    get_store_string(app, store_keys::STT_PROVIDER)
-        .map(|s| parakeet::SttProvider::from_store_value(&s))
-        .unwrap_or(parakeet::SttProvider::Groq)
+/// "hold" = press to start, release to stop; anything else is toggle
+fn is_hold_to_talk_enabled(app: &AppHandle) -> bool {
+    get_store_string(app, store_keys::SHORTCUT_MODE)
+        .map(|v| v == "hold")
+        .unwrap_or(false)
 }
 
-fn get_language_from_store(app: &AppHandle) -> String {
-    get_store_string(app, store_keys::LANGUAGE).unwrap_or_else(|| "en".to_string())
+fn is_long_recording_enabled(app: &AppHandle) -> bool {
+    get_store_string(app, store_keys::LONG_RECORDING_ENABLED)
+        .map(|v| v == "true")
+        .unwrap_or(false)
 }
 
-fn get_cancel_shortcut_from_store(app: &AppHandle) -> String {
-    get_store_string(app, store_keys::CANCEL_SHORTCUT).unwrap_or_else(|| "Escape".to_string())
+fn is_auto_stop_enabled(app: &AppHandle) -> bool {
+    get_store_string(app, store_keys::AUTO_STOP_ENABLED)
+        .map(|v| v == "true")
+        .unwrap_or(false)
 }
 
-fn get_transcription_rules_from_store(app: &AppHandle) -> Vec<llm::TranscriptionRule> {
-    get_store_string(app, store_keys::TRANSCRIPTION_RULES)
-        .and_then(|s| serde_json::from_str(&s).ok())
-        .unwrap_or_default()
+/// Build the auto-stop settings from the store. The threshold is the minimum
+/// RMS level (0.0-1.0) that counts as speech.
+fn get_auto_stop_config(app: &AppHandle, on_silence: mpsc::Sender<()>) -> audio::AutoStop {
+    let silence_seconds = get_store_string(app, store_keys::AUTO_STOP_SILENCE_SECONDS)
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(DEFAULT_AUTO_STOP_SILENCE_SECONDS)
+        .max(MIN_AUTO_STOP_SILENCE_SECONDS);
+
+    let mut vad = vad::VadConfig::default();
+    if let Some(threshold) = get_store_string(app, store_keys::AUTO_STOP_THRESHOLD)
+        .and_then(|v| v.parse::<f32>().ok())
+        .filter(|t| *t > 0.0 && *t < 1.0)
+    {
+        vad.min_rms = threshold;
+    }
+
+    audio::AutoStop {
+        silence_timeout: std::time::Duration::from_secs_f64(silence_seconds),
+        vad,
+        on_silence,
+    }
 }
 
 fn should_skip_rules(app: &AppHandle) -> bool {
@@ -994,6 +2171,118 @@ async fn update_pure_paste_shortcut(app: AppHandle) -> Result<(), String> {
     register_pure_paste_shortcut_internal(&app)
 }
 
+/// Unregister the old re-run shortcut and register the configured one, if any.
+/// The shortcut is disabled while no key combination is set.
+fn register_rerun_shortcut_internal(app: &AppHandle) -> Result<(), String> {
+    let mut guard = REGISTERED_RERUN_SHORTCUT
+        .lock()
+        .map_err(|e| format!("Mutex poisoned: {}", e))?;
+
+    if let Some(ref old_str) = *guard {
+        if let Ok(old) = old_str.parse::<Shortcut>() {
+            app.global_shortcut().unregister(old).ok();
+        }
+    }
+    *guard = None;
+
+    let shortcut_str = match get_store_string(app, store_keys::RERUN_SHORTCUT)
+        .filter(|s| !s.trim().is_empty())
+    {
+        Some(s) => s,
+        None => return Ok(()),
+    };
+    let shortcut: Shortcut = shortcut_str.parse().map_err(|e| format!("{:?}", e))?;
+
+    let app_clone = app.clone();
+    app.global_shortcut()
+        .on_shortcut(shortcut, move |_app, _shortcut, event| {
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            let app = app_clone.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = rerun_last_dictation(app.clone(), None).await {
+                    eprintln!("Re-run failed: {}", e);
+                    show_error(&app, &e);
+                }
+            });
+        })
+        .map_err(|e| e.to_string())?;
+
+    *guard = Some(shortcut_str);
+
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_rerun_shortcut(app: AppHandle) -> Result<(), String> {
+    register_rerun_shortcut_internal(&app)
+}
+
+/// Register the shortcut -> mode id map from the store, replacing the previous
+/// set. Each shortcut records like the main one but in its own mode ("none"
+/// records raw, without rules). A shortcut that fails to register doesn't
+/// prevent the others; the failures are returned together.
+fn register_mode_shortcuts_internal(app: &AppHandle) -> Result<(), String> {
+    let mut guard = REGISTERED_MODE_SHORTCUTS
+        .lock()
+        .map_err(|e| format!("Mutex poisoned: {}", e))?;
+
+    for old_str in guard.drain(..) {
+        if let Ok(old) = old_str.parse::<Shortcut>() {
+            app.global_shortcut().unregister(old).ok();
+        }
+    }
+
+    let mode_shortcuts: HashMap<String, String> =
+        get_store_string(app, store_keys::MODE_SHORTCUTS)
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+
+    let mut errors = Vec::new();
+    for (shortcut_str, mode_id) in mode_shortcuts {
+        if shortcut_str.trim().is_empty() {
+            continue;
+        }
+        let shortcut: Shortcut = match shortcut_str.parse() {
+            Ok(shortcut) => shortcut,
+            Err(e) => {
+                errors.push(format!("{}: {:?}", shortcut_str, e));
+                continue;
+            }
+        };
+
+        let app_clone = app.clone();
+        let result = app
+            .global_shortcut()
+            .on_shortcut(shortcut, move |_app, shortcut, event| {
+                handle_recording_shortcut(
+                    app_clone.clone(),
+                    shortcut,
+                    event.state,
+                    Some(mode_id.clone()),
+                );
+            });
+        match result {
+            Ok(()) => guard.push(shortcut_str),
+            Err(e) => errors.push(format!("{}: {}", shortcut_str, e)),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        let message = format!("Failed to register mode shortcuts: {}", errors.join(", "));
+        eprintln!("[Dictato] {}", message);
+        Err(message)
+    }
+}
+
+#[tauri::command]
+async fn update_mode_shortcuts(app: AppHandle) -> Result<(), String> {
+    register_mode_shortcuts_internal(&app)
+}
+
 #[tauri::command]
 fn get_recording_state() -> bool {
     IS_RECORDING.load(Ordering::SeqCst)
@@ -1020,7 +2309,7 @@ fn save_floating_position(app: AppHandle, x: f64, y: f64) {
 
 #[tauri::command]
 async fn generate_mode_prompt(app: AppHandle, name: String, description: String) -> Result<String, String> {
-    let provider = get_llm_provider_from_store(&app);
+    let provider = get_llm_provider_from_store(&app)?;
     let provider_name = get_llm_provider_name(&provider);
     let api_key = get_llm_api_key_for_provider(&app, &provider)
         .ok_or_else(|| format!("{} API key required for prompt generation. Add it in Settings.", provider_name))?;
@@ -1032,15 +2321,11 @@ async fn generate_mode_prompt(app: AppHandle, name: String, description: String)
 /// Uses the API key stored in settings for that provider.
 #[tauri::command]
 async fn list_llm_models(app: AppHandle, provider: String) -> Result<Vec<llm::ModelInfo>, String> {
-    let provider = match provider.as_str() {
-        "openai" => llm::LlmProvider::OpenAI,
-        "google" => llm::LlmProvider::Google,
-        "anthropic" => llm::LlmProvider::Anthropic,
-        other => return Err(format!("Unknown LLM provider: {}", other)),
-    };
+    let provider = parse_llm_provider(&app, &provider)?;
     let provider_name = get_llm_provider_name(&provider);
+    let key_optional = matches!(provider, llm::LlmProvider::Custom { .. });
     let api_key = get_llm_api_key_for_provider(&app, &provider)
-        .filter(|k| !k.trim().is_empty())
+        .filter(|k| key_optional || !k.trim().is_empty())
         .ok_or_else(|| format!("{} API key required to list models. Add it in Settings.", provider_name))?;
     llm::list_models(&provider, &api_key).await
 }
@@ -1197,6 +2482,268 @@ fn get_autostart(_app: AppHandle) -> Result<bool, String> {
     Ok(false)
 }
 
+// ============== Dictionary commands ==============
+
+#[tauri::command]
+fn get_dictionary(app: AppHandle) -> Vec<dictionary::DictionaryEntry> {
+    get_dictionary_from_store(&app)
+}
+
+/// Replace the dictionary; entries are trimmed and de-duplicated before saving
+#[tauri::command]
+fn set_dictionary(
+    app: AppHandle,
+    entries: Vec<dictionary::DictionaryEntry>,
+) -> Result<Vec<dictionary::DictionaryEntry>, String> {
+    let entries = dictionary::normalize(entries);
+    let json = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    let store = app.store("settings.json").map_err(|e| e.to_string())?;
+    store.set(store_keys::DICTIONARY, serde_json::json!(json));
+    store.save().map_err(|e| e.to_string())?;
+    println!("[Dictionary] Saved {} term(s)", entries.len());
+    Ok(entries)
+}
+
+// ============== Local rule commands ==============
+
+#[tauri::command]
+fn get_local_rules(app: AppHandle) -> Vec<local_rules::LocalRule> {
+    get_local_rules_from_store(&app)
+}
+
+/// Replace the local rules; fails without saving if any pattern is invalid
+#[tauri::command]
+fn set_local_rules(app: AppHandle, rules: Vec<local_rules::LocalRule>) -> Result<(), String> {
+    local_rules::validate(&rules)?;
+    let json = serde_json::to_string(&rules).map_err(|e| e.to_string())?;
+    let store = app.store("settings.json").map_err(|e| e.to_string())?;
+    store.set(store_keys::LOCAL_RULES, serde_json::json!(json));
+    store.save().map_err(|e| e.to_string())?;
+    println!("[Dictato] Saved {} local rule(s)", rules.len());
+    Ok(())
+}
+
+/// Run text through the given rules, for previewing them in settings
+#[tauri::command]
+fn preview_local_rules(text: String, rules: Vec<local_rules::LocalRule>) -> Result<String, String> {
+    local_rules::validate(&rules)?;
+    Ok(local_rules::apply(&text, &rules).0)
+}
+
+/// Built-in spoken commands for a language, for display in settings
+#[tauri::command]
+fn get_builtin_voice_commands(language: String) -> Vec<voice_commands::VoiceCommand> {
+    voice_commands::builtin_commands(&language)
+}
+
+// ============== Retry queue ==============
+
+fn queued_recording_engine(
+    app: &AppHandle,
+    item: &retry_queue::QueuedRecording,
+) -> Result<std::sync::Arc<dyn stt::SttEngine>, String> {
+    // An unknown provider must not be retried on some other engine, which
+    // could upload audio meant for a local model or another server
+    let provider = stt::SttProvider::parse(&item.provider)
+        .ok_or_else(|| format!("Unknown STT provider '{}'", item.provider))?;
+    app.state::<stt::SttRegistry>().get(&provider)
+}
+
+/// Transcribe one queued recording with the engine it was recorded with and
+/// post-process it like a live dictation, with the mode that was in effect.
+/// On success it moves to history and the floating window says so; it isn't
+/// inserted, since the app that had focus back then is long gone. The last
+/// dictation is left alone, so re-run still acts on what the user dictated
+/// most recently. On failure its next retry is scheduled.
+async fn retry_queued_recording_internal(app: &AppHandle, id: &str) -> Result<String, String> {
+    let queue = app.state::<retry_queue::RetryQueue>().inner().clone();
+    let item = queue.get(id)?;
+    let engine = queued_recording_engine(app, &item)?;
+    engine.check_ready(app)?;
+
+    let options = stt::TranscribeOptions {
+        language: item.language.clone(),
+        dictionary: get_dictionary_from_store(app),
+        progress: None,
+    };
+    let wav_path = queue.wav_path(id)?;
+    println!("[RetryQueue] Retrying {} (attempt {})", id, item.attempts + 1);
+
+    let result = transcribe_disk_recording(
+        app,
+        std::slice::from_ref(&engine),
+        &wav_path,
+        &options,
+        Feedback::Quiet,
+    )
+    .await
+    .map_err(|failure| failure.message());
+    match result {
+        Ok((text, _)) => {
+            // Recordings queued before the mode was stored use the current selection
+            let transformation = match item.mode_id.as_deref() {
+                Some(mode_id) => Transformation::from_mode_id(mode_id),
+                None => transformation_from_store(app, None),
+            };
+            // Runs unattended, so it must not touch the floating window of a
+            // recording the user may have started meanwhile
+            let (raw_text, transformed) = process_dictation(
+                app,
+                text.trim().to_string(),
+                &item.language,
+                transformation,
+                Feedback::Quiet,
+            )
+            .await;
+            if let Some(e) = &transformed.llm_error {
+                eprintln!("[RetryQueue] {}: keeping the raw transcript: {}", id, e);
+            }
+            let final_text = transformed.text;
+            queue.remove(id)?;
+            println!("[RetryQueue] {} transcribed, removed from queue", id);
+
+            if !raw_text.is_empty() {
+                record_history(
+                    app,
+                    history::NewEntry {
+                        source: history::Source::Dictation,
+                        source_name: None,
+                        processed_text: (final_text != raw_text).then(|| final_text.clone()),
+                        raw_text,
+                        mode_id: transformed.applied_mode,
+                        rules_applied: transformed.rules_applied,
+                        llm_provider: transformed.llm_provider,
+                        engine: item.engine.clone(),
+                        language: item.language.clone(),
+                        duration_seconds: item.duration_seconds,
+                        segments: Vec::new(),
+                        words: Vec::new(),
+                    },
+                );
+                // Unless a new recording has the window
+                if !IS_RECORDING.load(Ordering::SeqCst) {
+                    show_floating_message(
+                        app,
+                        "retry-queue-transcribed",
+                        serde_json::json!({ "id": id, "text": final_text }),
+                    );
+                }
+            }
+            app.emit("retry-queue-updated", ()).ok();
+            Ok(final_text)
+        }
+        Err(e) => {
+            let item = queue.record_failure(id, &e)?;
+            println!(
+                "[RetryQueue] {} failed again ({} attempt(s)): {}",
+                id, item.attempts, e
+            );
+            app.emit("retry-queue-updated", ()).ok();
+            Err(e)
+        }
+    }
+}
+
+/// Background task retrying queued recordings whose backoff has elapsed.
+/// Recordings whose engine isn't ready (e.g. API key removed) are paused
+/// until it is, without using up attempts.
+async fn run_retry_queue_worker(app: AppHandle) {
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(RETRY_QUEUE_POLL_SECONDS)).await;
+        if IS_RECORDING.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        let _guard = RETRY_QUEUE_LOCK.lock().await;
+        let due: Vec<retry_queue::QueuedRecording> = match app
+            .state::<retry_queue::RetryQueue>()
+            .list()
+        {
+            Ok(items) => items.into_iter().filter(|item| item.is_due()).collect(),
+            Err(_) => continue,
+        };
+        for item in due {
+            let ready = queued_recording_engine(&app, &item).and_then(|engine| engine.check_ready(&app));
+            if ready.is_err() {
+                continue;
+            }
+            if let Err(e) = retry_queued_recording_internal(&app, &item.id).await {
+                println!("[RetryQueue] Automatic retry of {} failed: {}", item.id, e);
+            }
+        }
+    }
+}
+
+#[tauri::command]
+fn list_queued_recordings(
+    queue: tauri::State<'_, retry_queue::RetryQueue>,
+) -> Result<Vec<retry_queue::QueuedRecording>, String> {
+    queue.list()
+}
+
+/// Retry a queued recording now, regardless of its backoff. Returns the transcript.
+#[tauri::command]
+async fn retry_queued_recording(app: AppHandle, id: String) -> Result<String, String> {
+    let _guard = RETRY_QUEUE_LOCK.lock().await;
+    retry_queued_recording_internal(&app, &id).await
+}
+
+#[tauri::command]
+fn discard_queued_recording(app: AppHandle, id: String) -> Result<(), String> {
+    app.state::<retry_queue::RetryQueue>().remove(&id)?;
+    println!("[RetryQueue] Discarded {}", id);
+    app.emit("retry-queue-updated", ()).ok();
+    Ok(())
+}
+
+// ============== History commands ==============
+
+#[tauri::command]
+fn list_history(
+    app: AppHandle,
+    offset: u32,
+    limit: u32,
+    query: Option<String>,
+    sources: Option<Vec<String>>,
+) -> Result<history::HistoryPage, String> {
+    app.state::<history::HistoryState>()
+        .list(offset, limit, query.as_deref(), sources.as_deref())
+}
+
+/// File and YouTube results used to be kept by the frontend in the settings
+/// store; move them into the database once so there is a single history
+fn import_legacy_history(app: &AppHandle) {
+    let Some(json) = get_store_string(app, store_keys::TRANSCRIPTION_HISTORY) else {
+        return;
+    };
+    match app.state::<history::HistoryState>().import_legacy(&json) {
+        Ok(count) => println!("[History] Imported {} entries from the settings store", count),
+        Err(e) => {
+            eprintln!("[History] {}", e);
+            return;
+        }
+    }
+    if let Ok(store) = app.store("settings.json") {
+        store.delete(store_keys::TRANSCRIPTION_HISTORY);
+        store.save().ok();
+    }
+}
+
+#[tauri::command]
+fn get_history_entry(app: AppHandle, id: i64) -> Result<Option<history::HistoryEntry>, String> {
+    app.state::<history::HistoryState>().get(id)
+}
+
+#[tauri::command]
+fn delete_history_entry(app: AppHandle, id: i64) -> Result<(), String> {
+    app.state::<history::HistoryState>().delete(id)
+}
+
+#[tauri::command]
+fn clear_history(app: AppHandle, sources: Option<Vec<String>>) -> Result<(), String> {
+    app.state::<history::HistoryState>().clear(sources.as_deref())
+}
+
 // ============== Transcribe commands ==============
 
 /// Progress stages for transcription
@@ -1244,6 +2791,31 @@ async fn transcribe_file(
     language: String,
     mode_id: Option<String>,
     apply_rules: bool,
+) -> Result<transcribe::TranscriptionResult, String> {
+    let source_name = std::path::Path::new(&file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_path.clone());
+    transcribe_file_with_source(
+        app,
+        file_path,
+        language,
+        mode_id,
+        apply_rules,
+        history::Source::File,
+        source_name,
+    )
+    .await
+}
+
+async fn transcribe_file_with_source(
+    app: AppHandle,
+    file_path: String,
+    language: String,
+    mode_id: Option<String>,
+    apply_rules: bool,
+    source: history::Source,
+    source_name: String,
 ) -> Result<transcribe::TranscriptionResult, String> {
     use std::path::Path;
 
@@ -1259,10 +2831,34 @@ async fn transcribe_file(
         return Err("Unsupported file format. Supported: MP3, WAV, M4A, OGG, FLAC, MP4, MOV, WebM".to_string());
     }
 
-    let stt_provider = get_stt_provider_from_store(&app);
-    let llm_provider = get_llm_provider_from_store(&app);
-    let llm_api_key = get_llm_api_key_for_provider(&app, &llm_provider);
-    let llm_model = get_llm_model_for_provider(&app, &llm_provider);
+    let engine = get_stt_engine(&app)?;
+    engine.check_ready(&app)?;
+    let llm_targets = get_llm_targets(&app).unwrap_or_else(|e| {
+        // Skip the mode or rules rather than use a provider the user didn't pick
+        if mode_id.is_some() || apply_rules {
+            show_error(&app, &e);
+        }
+        Vec::new()
+    });
+    let progress_app = app.clone();
+    let engine_name = engine.display_name();
+    let options = stt::TranscribeOptions {
+        language,
+        dictionary: get_dictionary_from_store(&app),
+        progress: Some(std::sync::Arc::new(move |index: usize, total: usize| {
+            if total < 2 {
+                return;
+            }
+            let progress = progress_percent::TRANSCRIBE_START
+                + ((index as f32 / total as f32) * 50.0) as u32;
+            emit_transcribe_progress(
+                &progress_app,
+                progress_stages::TRANSCRIBING,
+                progress,
+                &format!("Transcribing with {} (part {} of {})...", engine_name, index + 1, total),
+            );
+        })),
+    };
 
     emit_transcribe_progress(&app, progress_stages::PREPARING, progress_percent::PREPARING, "Preparing file...");
 
@@ -1270,11 +2866,18 @@ async fn transcribe_file(
     let temp_dir = transcribe::create_temp_dir()?;
     let temp_path = temp_dir.path();
 
-    // Get audio file path (extract from video if needed)
-    let audio_path = if transcribe::is_supported_video(path) {
+    // Get audio file path. Local engines decode video containers themselves;
+    // uploads need the audio extracted, natively if ffmpeg isn't installed.
+    let audio_path = if transcribe::is_supported_video(path) && engine.provider().is_remote() {
         emit_transcribe_progress(&app, progress_stages::EXTRACTING, progress_percent::EXTRACTING, "Extracting audio from video...");
 
-        transcribe::extract_audio_from_video(path, temp_path)?
+        if transcribe::check_ffmpeg().0 {
+            transcribe::extract_audio_from_video(path, temp_path)?
+        } else {
+            let wav_path = temp_path.join("extracted_audio.wav");
+            decode::decode_to_wav(path, &wav_path, decode::SPEECH_SAMPLE_RATE)?;
+            wav_path
+        }
     } else {
         path.to_path_buf()
     };
@@ -1282,84 +2885,66 @@ async fn transcribe_file(
     // Get duration for stats
     let duration = transcribe::get_audio_duration(&audio_path).unwrap_or(0.0);
 
-    // Transcribe using the selected STT provider
-    let raw_text = if stt_provider == parakeet::SttProvider::Parakeet {
-        emit_transcribe_progress(&app, progress_stages::TRANSCRIBING, progress_percent::TRANSCRIBE_SINGLE, "Transcribing locally...");
-
-        parakeet::set_transcribing(true);
-        let parakeet_state = app.state::<parakeet::ParakeetState>();
-        let state_clone = parakeet_state.inner().clone();
-        let audio_path_clone = audio_path.clone();
-
-        let result = tokio::task::spawn_blocking(move || {
-            let r = parakeet::transcribe_file_local(&state_clone, &audio_path_clone);
-            parakeet::set_transcribing(false);
-            r
-        })
-        .await
-        .map_err(|e| {
-            parakeet::set_transcribing(false);
-            format!("Transcription task failed: {}", e)
-        })?;
-        result?
-    } else if stt_provider == parakeet::SttProvider::Whisper {
-        emit_transcribe_progress(&app, progress_stages::TRANSCRIBING, progress_percent::TRANSCRIBE_SINGLE, "Transcribing locally with Whisper...");
-
-        parakeet::set_transcribing(true);
-        let whisper_state = app.state::<whisper::WhisperState>();
-        let state_clone = whisper_state.inner().clone();
-        let audio_path_clone = audio_path.clone();
-        let lang = language.clone();
-
-        let result = tokio::task::spawn_blocking(move || {
-            let r = whisper::transcribe_file_local(&state_clone, &audio_path_clone, &lang);
-            parakeet::set_transcribing(false);
-            r
-        })
-        .await
-        .map_err(|e| {
-            parakeet::set_transcribing(false);
-            format!("Transcription task failed: {}", e)
-        })?;
-        result?
-    } else {
-        let groq_api_key = get_groq_api_key_from_store(&app)
-            .ok_or("Groq API key required. Add it in Settings.")?;
+    // Transcribe using the selected STT engine
+    let (raw_text, mut segments, words) = if engine.needs_chunking(&audio_path)? {
+        emit_transcribe_progress(&app, progress_stages::SPLITTING, progress_percent::SPLITTING, "Splitting large file...");
 
-        if transcribe::needs_chunking(&audio_path)? {
-            emit_transcribe_progress(&app, progress_stages::SPLITTING, progress_percent::SPLITTING, "Splitting large file...");
+        let chunks = chunking::split_into_wav_chunks(
+            &audio_path,
+            temp_path,
+            transcribe::CHUNK_DURATION_SECONDS as f64,
+        )?;
+        let total_chunks = chunks.len();
+        let mut stitcher = chunking::Stitcher::default();
 
-            let chunks = transcribe::split_audio_file(&audio_path, temp_path, transcribe::CHUNK_DURATION_SECONDS)?;
-            let total_chunks = chunks.len();
-            let mut transcripts = Vec::new();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let progress = progress_percent::TRANSCRIBE_START + ((i as f32 / total_chunks as f32) * 50.0) as u32;
+            emit_transcribe_progress(&app, progress_stages::TRANSCRIBING, progress, "Transcribing audio...");
 
-            for (i, chunk_path) in chunks.iter().enumerate() {
-                let progress = progress_percent::TRANSCRIBE_START + ((i as f32 / total_chunks as f32) * 50.0) as u32;
-                emit_transcribe_progress(&app, progress_stages::TRANSCRIBING, progress, "Transcribing audio...");
+            let transcript = engine.transcribe_file_timed(&app, &chunk.path, &options).await?;
+            stitcher.push(transcript, chunk.start_secs, chunk.overlap_secs);
+        }
 
-                let chunk_text = groq::transcribe_file(&groq_api_key, chunk_path, &language).await?;
-                transcripts.push(chunk_text);
-            }
+        let transcript = stitcher.finish();
+        (transcript.text, transcript.segments, transcript.words)
+    } else {
+        emit_transcribe_progress(
+            &app,
+            progress_stages::TRANSCRIBING,
+            progress_percent::TRANSCRIBE_SINGLE,
+            &format!("Transcribing with {}...", engine.display_name()),
+        );
 
-            transcripts.join(" ")
-        } else {
-            emit_transcribe_progress(&app, progress_stages::TRANSCRIBING, progress_percent::TRANSCRIBE_SINGLE, "Transcribing audio...");
+        let transcript = engine.transcribe_file_timed(&app, &audio_path, &options).await?;
+        (transcript.text, transcript.segments, transcript.words)
+    };
 
-            groq::transcribe_file(&groq_api_key, &audio_path, &language).await?
+    // Local find/replace rules run before any LLM step, as for dictation, so
+    // they work offline. Segments get them too so exported subtitles match the
+    // text. Words are left as recognized: a rule can span several words or
+    // insert spaces, which would break one-word-per-timing entries.
+    let ruled_text = if (mode_id.is_some() || apply_rules) && !raw_text.is_empty() {
+        let rules = local_rules::RuleSet::new(&get_local_rules_from_store(&app));
+        for segment in segments.iter_mut() {
+            segment.text = rules.apply(&segment.text).0;
+        }
+        let (text, changed) = rules.apply(&raw_text);
+        if changed > 0 {
+            println!("[Dictato] {} local rule(s) applied", changed);
         }
+        text
+    } else {
+        raw_text.clone()
     };
 
     // Apply mode or rules if requested
-    let processed_text = if !raw_text.is_empty() {
+    let processed = if !raw_text.is_empty() {
         if let Some(ref mode) = mode_id {
             if let Some(prompt) = get_mode_prompt_from_store(&app, mode) {
-                if let Some(ref llm_key) = llm_api_key {
+                if !llm_targets.is_empty() {
                     emit_transcribe_progress(&app, progress_stages::PROCESSING, progress_percent::PROCESSING, "Applying mode...");
 
-                    match llm::process_with_prompt(&llm_provider, llm_key, &llm_model, &raw_text, &prompt).await {
-                        Ok(processed) => Some(processed),
-                        Err(_) => None,
-                    }
+                    llm::process_with_prompt(&llm_targets, &ruled_text, &prompt, None).await.ok()
                 } else {
                     None
                 }
@@ -1370,13 +2955,10 @@ async fn transcribe_file(
             let rules = get_transcription_rules_from_store(&app);
             let has_enabled_rules = rules.iter().any(|r| r.enabled);
             if has_enabled_rules {
-                if let Some(ref llm_key) = llm_api_key {
+                if !llm_targets.is_empty() {
                     emit_transcribe_progress(&app, progress_stages::PROCESSING, progress_percent::PROCESSING, "Applying rules...");
 
-                    match llm::process_with_rules(&llm_provider, llm_key, &llm_model, &raw_text, rules).await {
-                        Ok(processed) => Some(processed),
-                        Err(_) => None,
-                    }
+                    llm::process_with_rules(&llm_targets, &ruled_text, rules, None).await.ok()
                 } else {
                     None
                 }
@@ -1390,19 +2972,67 @@ async fn transcribe_file(
         None
     };
 
+    let llm_provider = processed
+        .as_ref()
+        .and_then(|outcome| describe_llm_target(&app, &llm_targets, outcome.target_index));
+    let processed_text = processed
+        .map(|outcome| outcome.text)
+        .or_else(|| (ruled_text != raw_text).then_some(ruled_text));
+
     emit_transcribe_progress(&app, progress_stages::COMPLETE, progress_percent::COMPLETE, "Complete!");
 
     let final_text = processed_text.as_ref().unwrap_or(&raw_text);
     let word_count = final_text.split_whitespace().count();
 
+    if !raw_text.is_empty() {
+        let rules_applied = processed_text.is_some() && apply_rules && mode_id.is_none();
+        record_history(
+            &app,
+            history::NewEntry {
+                source,
+                source_name: Some(source_name),
+                raw_text: raw_text.clone(),
+                processed_text: processed_text.clone(),
+                mode_id: mode_id.filter(|_| processed_text.is_some()),
+                rules_applied,
+                llm_provider,
+                engine: engine.display_name().to_string(),
+                language: options.language.clone(),
+                duration_seconds: duration,
+                segments: segments.clone(),
+                words: words.clone(),
+            },
+        );
+    }
+
     Ok(transcribe::TranscriptionResult {
         raw_text,
         processed_text,
         duration_seconds: duration,
         word_count,
+        segments,
+        words,
     })
 }
 
+/// Write the segments of a file transcription as SRT, WebVTT or JSON
+#[tauri::command]
+fn export_subtitles(
+    segments: Vec<subtitles::Segment>,
+    format: String,
+    output_path: String,
+) -> Result<(), String> {
+    if segments.is_empty() {
+        return Err("This transcription has no timestamps to export".to_string());
+    }
+    let format = subtitles::SubtitleFormat::parse(&format)?;
+    let content = subtitles::render(&segments, format)?;
+    std::fs::write(&output_path, content)
+        .map_err(|e| format!("Failed to write subtitles: {}", e))?;
+    println!("[Dictato] Exported {} segments to {}", segments.len(), output_path);
+    Ok(())
+}
+
 #[tauri::command]
 async fn transcribe_youtube(
     app: AppHandle,
@@ -1432,26 +3062,8 @@ async fn transcribe_youtube(
     }
     println!("[YouTube] Dependencies OK: yt-dlp={:?}, ffmpeg={:?}", deps.yt_dlp_version, deps.ffmpeg_version);
 
-    // Validate STT provider is ready
-    let stt_provider = get_stt_provider_from_store(&app);
-    match stt_provider {
-        parakeet::SttProvider::Parakeet => {
-            let parakeet_state = app.state::<parakeet::ParakeetState>();
-            if !parakeet::is_model_loaded(&parakeet_state) {
-                return Err("Parakeet model not loaded. Download it in Settings.".to_string());
-            }
-        }
-        parakeet::SttProvider::Whisper => {
-            let whisper_state = app.state::<whisper::WhisperState>();
-            if !whisper::is_model_loaded(&whisper_state) {
-                return Err("Whisper model not loaded. Download it in Settings.".to_string());
-            }
-        }
-        parakeet::SttProvider::Groq => {
-            get_groq_api_key_from_store(&app)
-                .ok_or("Groq API key required. Add it in Settings.")?;
-        }
-    }
+    // Validate STT engine is ready
+    get_stt_engine(&app)?.check_ready(&app)?;
 
     emit_transcribe_progress(&app, progress_stages::DOWNLOADING, progress_percent::YOUTUBE_START, "Starting YouTube download...");
 
@@ -1482,7 +3094,16 @@ async fn transcribe_youtube(
 
     // Now process like a regular file
     let file_path = audio_path.to_string_lossy().to_string();
-    transcribe_file(app, file_path, language, mode_id, apply_rules).await
+    transcribe_file_with_source(
+        app,
+        file_path,
+        language,
+        mode_id,
+        apply_rules,
+        history::Source::YouTube,
+        url,
+    )
+    .await
 }
 
 fn create_floating_window(app: &AppHandle) -> Result<(), String> {
@@ -1655,15 +3276,26 @@ fn format_llm_error(error: &str) -> String {
     if error.contains("500") || error.contains("502") || error.contains("503") {
         return "API service error. Try again later.".to_string();
     }
+    if error.contains("custom LLM endpoint") {
+        return error.to_string();
+    }
+    if error.contains("error sending request") {
+        return "Could not reach the LLM server. Check that it is running.".to_string();
+    }
     // Default: show a generic message
     "Processing failed. Check your API key and try again.".to_string()
 }
 
 fn show_error(app: &AppHandle, message: &str) {
+    show_floating_message(app, "transcription-error", message);
+}
+
+/// Show the floating window for a few seconds with a message event
+fn show_floating_message<S: serde::Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
     if let Some(window) = app.get_webview_window("floating") {
         window.show().ok();
         app.emit("floating-expanded", true).ok();
-        app.emit("transcription-error", message).ok();
+        app.emit(event, payload).ok();
 
         // Auto-hide after 3 seconds
         let app_clone = app.clone();
@@ -1785,7 +3417,10 @@ pub fn run() {
         .manage(AudioCaptureState::default())
         .manage(parakeet::ParakeetState::default())
         .manage(whisper::WhisperState::default())
+        .manage(stt::SttRegistry::default())
         .manage(keyboard_lock::LockState::default())
+        .manage(history::HistoryState::default())
+        .manage(retry_queue::RetryQueue::default())
         .invoke_handler(tauri::generate_handler![
             start_recording,
             stop_recording,
@@ -1797,6 +3432,9 @@ pub fn run() {
             register_cancel_shortcut,
             unregister_shortcuts,
             update_pure_paste_shortcut,
+            update_rerun_shortcut,
+            update_mode_shortcuts,
+            rerun_last_dictation,
             get_recording_state,
             list_audio_devices,
             save_floating_position,
@@ -1809,6 +3447,7 @@ pub fn run() {
             set_autostart,
             get_autostart,
             check_transcribe_dependencies,
+            export_subtitles,
             transcribe_file,
             transcribe_youtube,
             get_parakeet_model_status,
@@ -1820,6 +3459,19 @@ pub fn run() {
             engage_cleaning_mode,
             get_cleaning_mode_state,
             close_cleaning_overlay,
+            get_dictionary,
+            set_dictionary,
+            get_local_rules,
+            set_local_rules,
+            preview_local_rules,
+            get_builtin_voice_commands,
+            list_queued_recordings,
+            retry_queued_recording,
+            discard_queued_recording,
+            list_history,
+            get_history_entry,
+            delete_history_entry,
+            clear_history,
         ])
         .setup(|app| {
             // Hide app from macOS dock (stealth mode - tray icon only)
@@ -1829,79 +3481,69 @@ pub fn run() {
             setup_tray(app.handle())?;
             create_floating_window(app.handle()).ok();
 
-            // Register pure paste shortcut on startup (if previously enabled)
+            // Register pure paste, re-run and mode shortcuts on startup (if configured)
             register_pure_paste_shortcut_internal(app.handle()).ok();
-
-            // Check STT provider and show settings if needed
-            let stt_provider = get_stt_provider_from_store(app.handle());
-            match stt_provider {
-                parakeet::SttProvider::Parakeet => {
-                    let model_dir = match parakeet::get_model_dir(app.handle()) {
-                        Ok(dir) => dir,
-                        Err(e) => {
-                            eprintln!("[Parakeet] Failed to get model dir on startup: {}", e);
-                            show_main_window(app.handle());
-                            return Ok(());
-                        }
-                    };
-                    if parakeet::is_model_downloaded(&model_dir) {
-                        // Load model in background on startup.
-                        // Uses std::thread::spawn because setup() is not async.
-                        let parakeet_state = app.state::<parakeet::ParakeetState>();
-                        let state_clone = parakeet_state.inner().clone();
-                        let app_handle = app.handle().clone();
-                        std::thread::spawn(move || {
-                            app_handle.emit(parakeet::EVENT_LOADING, true).ok();
-                            match parakeet::load_model(&state_clone, &model_dir) {
-                                Ok(_) => {
-                                    app_handle.emit(parakeet::EVENT_LOADING, false).ok();
-                                }
-                                Err(e) => {
-                                    eprintln!("[Parakeet] Failed to load model on startup: {}", e);
-                                    app_handle.emit(parakeet::EVENT_LOADING, false).ok();
-                                }
-                            }
-                        });
-                    } else {
-                        show_main_window(app.handle());
+            register_rerun_shortcut_internal(app.handle()).ok();
+            register_mode_shortcuts_internal(app.handle()).ok();
+
+            // History and the retry queue are best-effort: the app keeps working without them
+            match app.path().app_data_dir() {
+                Ok(dir) => {
+                    match app.state::<history::HistoryState>().open(&dir) {
+                        Ok(()) => import_legacy_history(app.handle()),
+                        Err(e) => eprintln!("[History] {}", e),
                     }
-                }
-                parakeet::SttProvider::Whisper => {
-                    let model_dir = match whisper::get_model_dir(app.handle()) {
-                        Ok(dir) => dir,
-                        Err(e) => {
-                            eprintln!("[Whisper] Failed to get model dir on startup: {}", e);
-                            show_main_window(app.handle());
-                            return Ok(());
+                    match app.state::<retry_queue::RetryQueue>().open(&dir) {
+                        Ok(()) => {
+                            tauri::async_runtime::spawn(run_retry_queue_worker(app.handle().clone()));
                         }
-                    };
-                    if whisper::is_model_downloaded(&model_dir) {
-                        let whisper_state = app.state::<whisper::WhisperState>();
-                        let state_clone = whisper_state.inner().clone();
-                        let app_handle = app.handle().clone();
-                        std::thread::spawn(move || {
-                            app_handle.emit(whisper::EVENT_LOADING, true).ok();
-                            match whisper::load_model(&state_clone, &model_dir) {
-                                Ok(_) => {
-                                    app_handle.emit(whisper::EVENT_LOADING, false).ok();
-                                }
-                                Err(e) => {
-                                    eprintln!("[Whisper] Failed to load model on startup: {}", e);
-                                    app_handle.emit(whisper::EVENT_LOADING, false).ok();
-                                }
-                            }
-                        });
-                    } else {
-                        show_main_window(app.handle());
+                        Err(e) => eprintln!("[RetryQueue] {}", e),
                     }
                 }
-                parakeet::SttProvider::Groq => {
-                    let has_api_key = get_groq_api_key_from_store(app.handle()).is_some();
-                    if !has_api_key {
-                        show_main_window(app.handle());
-                    }
+                Err(e) => eprintln!("[History] Failed to get app data dir: {}", e),
+            }
+
+            // Load the models of the selected engine and of local engines in
+            // the fallback chain or app profiles, so they don't have to load
+            // on the first dictation that needs them
+            let stt_provider = get_stt_provider_from_store(app.handle());
+            let profile_providers: Vec<stt::SttProvider> =
+                get_app_profiles_from_store(app.handle())
+                    .iter()
+                    .filter(|p| p.enabled)
+                    .filter_map(|p| p.stt_provider.as_deref())
+                    .filter_map(stt::SttProvider::parse)
+                    .collect();
+            let mut local_providers: Vec<stt::SttProvider> = Vec::new();
+            for provider in std::iter::once(stt_provider.clone())
+                .chain(get_stt_fallback_providers(app.handle()))
+                .chain(profile_providers)
+            {
+                if provider.is_remote() || local_providers.contains(&provider) {
+                    continue;
+                }
+                match is_local_model_downloaded(app.handle(), &provider) {
+                    Ok(true) => local_providers.push(provider),
+                    Ok(false) if provider != stt_provider => eprintln!(
+                        "[Dictato] STT engine '{}' is configured but its model isn't downloaded",
+                        provider.as_store_value()
+                    ),
+                    Ok(false) => {}
+                    Err(e) => eprintln!(
+                        "[Dictato] Failed to check the '{}' model on startup: {}",
+                        provider.as_store_value(),
+                        e
+                    ),
                 }
             }
+            spawn_local_model_loads(app.handle(), local_providers);
+
+            // Show settings if the selected engine can't run
+            let ready =
+                get_stt_engine(app.handle()).and_then(|engine| engine.check_ready(app.handle()));
+            if ready.is_err() {
+                show_main_window(app.handle());
+            }
 
             Ok(())
         })
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 const LLM_TIMEOUT_SECS: u64 = 30;
+/// Streaming responses are read incrementally, so allow long rewrites to finish
+const LLM_STREAM_TIMEOUT_SECS: u64 = 120;
 
 // OpenAI
 const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
@@ -26,17 +28,29 @@ pub enum LlmProvider {
     OpenAI,
     Google,
     Anthropic,
+    /// Any server exposing the OpenAI chat completions API (Ollama,
+    /// llama.cpp server, LM Studio, vLLM, ...)
+    Custom { base_url: String },
 }
 
-/// Fallback model used when the user hasn't picked one (or the store is empty)
+/// Fallback model used when the user hasn't picked one (or the store is empty).
+/// Custom endpoints have no sensible default; the user must name a model.
 pub fn default_model(provider: &LlmProvider) -> &'static str {
     match provider {
         LlmProvider::OpenAI => DEFAULT_OPENAI_MODEL,
         LlmProvider::Google => DEFAULT_GOOGLE_MODEL,
         LlmProvider::Anthropic => DEFAULT_ANTHROPIC_MODEL,
+        LlmProvider::Custom { .. } => "",
     }
 }
 
+/// Reduce a user-entered endpoint to its root, accepting both
+/// "http://localhost:11434" and "http://localhost:11434/v1/"
+pub fn normalize_base_url(base_url: &str) -> String {
+    let trimmed = base_url.trim().trim_end_matches('/');
+    trimmed.strip_suffix("/v1").unwrap_or(trimmed).to_string()
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TranscriptionRule {
     pub id: String,
@@ -58,7 +72,13 @@ struct ChatRequest {
     model: String,
     messages: Vec<ChatMessage>,
     temperature: f32,
-    max_completion_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    /// Legacy limit field; self-hosted servers often don't know the newer one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -137,6 +157,8 @@ struct AnthropicRequest {
     max_tokens: u32,
     system: String,
     messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream: Option<bool>,
 }
 
 #[derive(Deserialize)]
@@ -156,7 +178,42 @@ async fn call_openai_chat(
     system_prompt: &str,
     user_content: &str,
 ) -> Result<String, String> {
-    let request = ChatRequest {
+    let request = chat_request(model, system_prompt, user_content, Some(4096), None);
+    call_chat_completions(OPENAI_API_URL, api_key, &request, "OpenAI").await
+}
+
+/// Call a self-hosted OpenAI-compatible chat completions endpoint.
+/// The API key is optional; local servers usually run without one.
+async fn call_custom_chat(
+    base_url: &str,
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+) -> Result<String, String> {
+    let url = custom_chat_url(base_url, model)?;
+    let request = chat_request(model, system_prompt, user_content, None, Some(4096));
+    call_chat_completions(&url, api_key, &request, "Custom endpoint").await
+}
+
+fn custom_chat_url(base_url: &str, model: &str) -> Result<String, String> {
+    if base_url.trim().is_empty() {
+        return Err("No base URL configured for the custom LLM endpoint".to_string());
+    }
+    if model.trim().is_empty() {
+        return Err("No model configured for the custom LLM endpoint".to_string());
+    }
+    Ok(format!("{}/v1/chat/completions", normalize_base_url(base_url)))
+}
+
+fn chat_request(
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+    max_completion_tokens: Option<u32>,
+    max_tokens: Option<u32>,
+) -> ChatRequest {
+    ChatRequest {
         model: model.to_string(),
         messages: vec![
             ChatMessage {
@@ -169,19 +226,33 @@ async fn call_openai_chat(
             },
         ],
         temperature: 0.3, // Low for consistency
-        max_completion_tokens: 4096,
-    };
+        max_completion_tokens,
+        max_tokens,
+        stream: None,
+    }
+}
 
+/// POST a chat completions request and return the first choice's content
+async fn call_chat_completions(
+    url: &str,
+    api_key: &str,
+    request: &ChatRequest,
+    provider_label: &str,
+) -> Result<String, String> {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(LLM_TIMEOUT_SECS))
         .build()
         .map_err(|e| format!("Failed to create client: {}", e))?;
 
-    let response = client
-        .post(OPENAI_API_URL)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
+    let mut builder = client
+        .post(url)
+        .header("Content-Type", "application/json");
+    if !api_key.trim().is_empty() {
+        builder = builder.header("Authorization", format!("Bearer {}", api_key));
+    }
+
+    let response = builder
+        .json(request)
         .send()
         .await
         .map_err(|e| format!("LLM request failed: {}", e))?;
@@ -189,7 +260,7 @@ async fn call_openai_chat(
     if !response.status().is_success() {
         let status = response.status();
         let body = response.text().await.unwrap_or_default();
-        return Err(format!("OpenAI API error {}: {}", status, body));
+        return Err(format!("{} API error {}: {}", provider_label, status, body));
     }
 
     let result: ChatResponse = response
@@ -204,13 +275,7 @@ async fn call_openai_chat(
         .ok_or_else(|| "No response from LLM".to_string())
 }
 
-/// Call Google Gemini API
-async fn call_google_chat(
-    api_key: &str,
-    model: &str,
-    system_prompt: &str,
-    user_content: &str,
-) -> Result<String, String> {
+fn gemini_request(system_prompt: &str, user_content: &str) -> GeminiRequest {
     // Gemini combines system prompt with user message
     let contents = vec![GeminiContentItem {
         role: "user".to_string(),
@@ -222,14 +287,23 @@ async fn call_google_chat(
         }],
     }];
 
-    let request = GeminiRequest {
+    GeminiRequest {
         contents,
         generation_config: GeminiGenerationConfig {
             temperature: 0.3,
             max_output_tokens: 4096,
         },
-    };
+    }
+}
 
+/// Call Google Gemini API
+async fn call_google_chat(
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+) -> Result<String, String> {
+    let request = gemini_request(system_prompt, user_content);
     let url = format!("{}/{}:generateContent?key={}", GOOGLE_API_BASE, model, api_key);
 
     let client = reqwest::Client::builder()
@@ -264,14 +338,8 @@ async fn call_google_chat(
         .ok_or_else(|| "No response from Gemini".to_string())
 }
 
-/// Call Anthropic Claude API
-async fn call_anthropic_chat(
-    api_key: &str,
-    model: &str,
-    system_prompt: &str,
-    user_content: &str,
-) -> Result<String, String> {
-    let request = AnthropicRequest {
+fn anthropic_request(model: &str, system_prompt: &str, user_content: &str) -> AnthropicRequest {
+    AnthropicRequest {
         model: model.to_string(),
         max_tokens: 4096,
         system: system_prompt.to_string(),
@@ -279,7 +347,18 @@ async fn call_anthropic_chat(
             role: "user".to_string(),
             content: user_content.to_string(),
         }],
-    };
+        stream: None,
+    }
+}
+
+/// Call Anthropic Claude API
+async fn call_anthropic_chat(
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+) -> Result<String, String> {
+    let request = anthropic_request(model, system_prompt, user_content);
 
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(LLM_TIMEOUT_SECS))
@@ -328,22 +407,440 @@ pub async fn call_llm_chat(
         LlmProvider::Anthropic => {
             call_anthropic_chat(api_key, model, system_prompt, user_content).await
         }
+        LlmProvider::Custom { base_url } => {
+            call_custom_chat(base_url, api_key, model, system_prompt, user_content).await
+        }
     }
 }
 
-/// Process transcript with transcription rules
-pub async fn process_with_rules(
+// ===== Streaming =====
+
+/// Receives the full text generated so far each time a new piece arrives, and
+/// an empty string when a fallback provider starts over
+pub type PartialCallback<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
+#[derive(Deserialize)]
+struct ChatStreamChunk {
+    #[serde(default)]
+    choices: Vec<ChatStreamChoice>,
+}
+
+#[derive(Deserialize)]
+struct ChatStreamChoice {
+    delta: ChatStreamDelta,
+}
+
+#[derive(Deserialize)]
+struct ChatStreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GeminiStreamChunk {
+    #[serde(default)]
+    candidates: Vec<GeminiStreamCandidate>,
+}
+
+#[derive(Deserialize)]
+struct GeminiStreamCandidate {
+    #[serde(default)]
+    content: Option<GeminiStreamContent>,
+}
+
+#[derive(Deserialize)]
+struct GeminiStreamContent {
+    #[serde(default)]
+    parts: Vec<GeminiStreamPart>,
+}
+
+#[derive(Deserialize)]
+struct GeminiStreamPart {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct AnthropicStreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    #[serde(default)]
+    delta: Option<AnthropicStreamDelta>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicStreamDelta {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+fn streaming_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(LLM_STREAM_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| format!("Failed to create client: {}", e))
+}
+
+/// Read a server-sent events body, passing the payload of every `data:` line
+/// to `on_data`. Lines are split on raw bytes so multi-byte characters that
+/// straddle network chunks stay intact.
+async fn for_each_sse_data<F>(mut response: reqwest::Response, mut on_data: F) -> Result<(), String>
+where
+    F: FnMut(&str) -> Result<(), String>,
+{
+    let mut pending: Vec<u8> = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("LLM stream interrupted: {}", e))?
+    {
+        pending.extend_from_slice(&chunk);
+        while let Some(newline) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim_start();
+            if data == "[DONE]" {
+                return Ok(());
+            }
+            on_data(data)?;
+        }
+    }
+    Ok(())
+}
+
+/// Accumulates streamed deltas and reports progress to the caller
+struct StreamAccumulator<'a> {
+    text: String,
+    on_partial: PartialCallback<'a>,
+}
+
+impl<'a> StreamAccumulator<'a> {
+    fn new(on_partial: PartialCallback<'a>) -> Self {
+        Self {
+            text: String::new(),
+            on_partial,
+        }
+    }
+
+    fn push(&mut self, delta: &str) {
+        if delta.is_empty() {
+            return;
+        }
+        self.text.push_str(delta);
+        (self.on_partial)(&self.text);
+    }
+
+    fn finish(self, provider_label: &str) -> Result<String, String> {
+        let text = self.text.trim();
+        if text.is_empty() {
+            return Err(format!("No response from {}", provider_label));
+        }
+        Ok(text.to_string())
+    }
+}
+
+async fn error_for_status(response: reqwest::Response, provider_label: &str) -> Result<reqwest::Response, String> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("{} API error {}: {}", provider_label, status, body))
+}
+
+/// Stream an OpenAI-style chat completion (OpenAI and custom endpoints)
+async fn stream_chat_completions(
+    url: &str,
+    api_key: &str,
+    mut request: ChatRequest,
+    provider_label: &str,
+    on_partial: PartialCallback<'_>,
+) -> Result<String, String> {
+    request.stream = Some(true);
+
+    let mut builder = streaming_client()?
+        .post(url)
+        .header("Content-Type", "application/json");
+    if !api_key.trim().is_empty() {
+        builder = builder.header("Authorization", format!("Bearer {}", api_key));
+    }
+    let response = builder
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| format!("LLM request failed: {}", e))?;
+    let response = error_for_status(response, provider_label).await?;
+
+    let mut output = StreamAccumulator::new(on_partial);
+    for_each_sse_data(response, |data| {
+        let chunk: ChatStreamChunk = serde_json::from_str(data)
+            .map_err(|e| format!("Failed to parse LLM stream: {}", e))?;
+        for choice in chunk.choices {
+            if let Some(content) = choice.delta.content {
+                output.push(&content);
+            }
+        }
+        Ok(())
+    })
+    .await?;
+    output.finish(provider_label)
+}
+
+async fn stream_google_chat(
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+    on_partial: PartialCallback<'_>,
+) -> Result<String, String> {
+    let request = gemini_request(system_prompt, user_content);
+    let url = format!(
+        "{}/{}:streamGenerateContent?alt=sse&key={}",
+        GOOGLE_API_BASE, model, api_key
+    );
+
+    let response = streaming_client()?
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| format!("Gemini request failed: {}", e))?;
+    let response = error_for_status(response, "Gemini").await?;
+
+    let mut output = StreamAccumulator::new(on_partial);
+    for_each_sse_data(response, |data| {
+        let chunk: GeminiStreamChunk = serde_json::from_str(data)
+            .map_err(|e| format!("Failed to parse Gemini stream: {}", e))?;
+        if let Some(content) = chunk.candidates.into_iter().next().and_then(|c| c.content) {
+            for part in content.parts {
+                output.push(&part.text);
+            }
+        }
+        Ok(())
+    })
+    .await?;
+    output.finish("Gemini")
+}
+
+async fn stream_anthropic_chat(
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+    on_partial: PartialCallback<'_>,
+) -> Result<String, String> {
+    let mut request = anthropic_request(model, system_prompt, user_content);
+    request.stream = Some(true);
+
+    let response = streaming_client()?
+        .post(ANTHROPIC_API_URL)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .header("Content-Type", "application/json")
+        .json(&request)
+        .send()
+        .await
+        .map_err(|e| format!("Anthropic request failed: {}", e))?;
+    let response = error_for_status(response, "Anthropic").await?;
+
+    let mut output = StreamAccumulator::new(on_partial);
+    for_each_sse_data(response, |data| {
+        let event: AnthropicStreamEvent = serde_json::from_str(data)
+            .map_err(|e| format!("Failed to parse Anthropic stream: {}", e))?;
+        match event.event_type.as_str() {
+            "content_block_delta" => {
+                if let Some(text) = event.delta.and_then(|d| d.text) {
+                    output.push(&text);
+                }
+            }
+            // Overload errors can arrive mid-stream after a 200 response
+            "error" => {
+                return Err(format!(
+                    "Anthropic API error: {}",
+                    event.error.map(|e| e.to_string()).unwrap_or_default()
+                ));
+            }
+            _ => {}
+        }
+        Ok(())
+    })
+    .await?;
+    output.finish("Anthropic")
+}
+
+/// Like [`call_llm_chat`], but streams the response and reports the text
+/// generated so far through `on_partial`. Returns the complete text.
+pub async fn call_llm_chat_streaming(
     provider: &LlmProvider,
     api_key: &str,
     model: &str,
+    system_prompt: &str,
+    user_content: &str,
+    on_partial: PartialCallback<'_>,
+) -> Result<String, String> {
+    match provider {
+        LlmProvider::OpenAI => {
+            let request = chat_request(model, system_prompt, user_content, Some(4096), None);
+            stream_chat_completions(OPENAI_API_URL, api_key, request, "OpenAI", on_partial).await
+        }
+        LlmProvider::Google => {
+            stream_google_chat(api_key, model, system_prompt, user_content, on_partial).await
+        }
+        LlmProvider::Anthropic => {
+            stream_anthropic_chat(api_key, model, system_prompt, user_content, on_partial).await
+        }
+        LlmProvider::Custom { base_url } => {
+            let url = custom_chat_url(base_url, model)?;
+            let request = chat_request(model, system_prompt, user_content, None, Some(4096));
+            stream_chat_completions(&url, api_key, request, "Custom endpoint", on_partial).await
+        }
+    }
+}
+
+/// Stream when the caller wants progress, otherwise wait for the full response
+async fn chat(
+    provider: &LlmProvider,
+    api_key: &str,
+    model: &str,
+    system_prompt: &str,
+    user_content: &str,
+    on_partial: Option<PartialCallback<'_>>,
+) -> Result<String, String> {
+    match on_partial {
+        Some(on_partial) => {
+            call_llm_chat_streaming(provider, api_key, model, system_prompt, user_content, on_partial).await
+        }
+        None => call_llm_chat(provider, api_key, model, system_prompt, user_content).await,
+    }
+}
+
+// ===== Fallback =====
+
+/// One provider/model/key combination in the fallback chain
+#[derive(Debug, Clone)]
+pub struct LlmTarget {
+    pub provider: LlmProvider,
+    pub api_key: String,
+    pub model: String,
+}
+
+/// Text produced by one of the targets of a fallback chain
+#[derive(Debug)]
+pub struct ChatOutcome {
+    pub text: String,
+    /// Index of the target that produced the text; `None` if no call was needed
+    pub target_index: Option<usize>,
+}
+
+impl ChatOutcome {
+    fn unchanged(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            target_index: None,
+        }
+    }
+}
+
+/// HTTP status of an "<Provider> API error 429 Too Many Requests: {...}"
+/// message, if it has one
+pub fn api_error_status(error: &str) -> Option<u16> {
+    let rest = error.split("API error ").nth(1)?;
+    rest.chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>()
+        .parse()
+        .ok()
+}
+
+/// Errors another provider may not run into: rate limits, quota, overload,
+/// server errors, timeouts and connection failures. Auth and request errors
+/// need the user's attention and are not retried elsewhere.
+pub fn is_retryable_error(error: &str) -> bool {
+    if let Some(status) = api_error_status(error) {
+        return status == 408 || status == 429 || status >= 500;
+    }
+    // The connection dropped after the provider had started answering
+    if error.starts_with("LLM stream interrupted:") {
+        return true;
+    }
+    let lower = error.to_lowercase();
+    [
+        "resource_exhausted",
+        "overloaded",
+        "rate limit",
+        "timed out",
+        "timeout",
+        "error sending request",
+        "connection refused",
+        "connection reset",
+        "connection closed",
+        "dns error",
+        "no response from",
+    ]
+    .iter()
+    .any(|marker| lower.contains(marker))
+}
+
+/// Try each target in order, moving on only when the error is retryable
+async fn chat_with_fallback(
+    targets: &[LlmTarget],
+    system_prompt: &str,
+    user_content: &str,
+    on_partial: Option<PartialCallback<'_>>,
+) -> Result<ChatOutcome, String> {
+    let mut first_error = None;
+    for (i, target) in targets.iter().enumerate() {
+        // A failed stream may have sent partial text already; clear it before
+        // the next provider starts over
+        if i > 0 {
+            if let Some(on_partial) = on_partial {
+                on_partial("");
+            }
+        }
+        match chat(&target.provider, &target.api_key, &target.model, system_prompt, user_content, on_partial).await {
+            Ok(text) => {
+                return Ok(ChatOutcome {
+                    text,
+                    target_index: Some(i),
+                })
+            }
+            Err(e) => {
+                let retryable = is_retryable_error(&e);
+                println!(
+                    "[LLM] {:?} / {} failed ({}): {}",
+                    target.provider,
+                    target.model,
+                    if retryable { "retryable" } else { "not retryable" },
+                    e
+                );
+                first_error.get_or_insert(e);
+                if !retryable {
+                    break;
+                }
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| "No LLM provider configured".to_string()))
+}
+
+/// Process transcript with transcription rules
+pub async fn process_with_rules(
+    targets: &[LlmTarget],
     transcript: &str,
     rules: Vec<TranscriptionRule>,
-) -> Result<String, String> {
+    on_partial: Option<PartialCallback<'_>>,
+) -> Result<ChatOutcome, String> {
     // Filter to only enabled rules
     let enabled_rules: Vec<_> = rules.iter().filter(|r| r.enabled).collect();
 
     if enabled_rules.is_empty() || transcript.trim().is_empty() {
-        return Ok(transcript.to_string());
+        return Ok(ChatOutcome::unchanged(transcript));
     }
 
     // Build the system prompt with rules
@@ -370,22 +867,21 @@ Output ONLY the formatted text with no explanations."#,
         rules_text
     );
 
-    call_llm_chat(provider, api_key, model, &system_prompt, transcript).await
+    chat_with_fallback(targets, &system_prompt, transcript, on_partial).await
 }
 
 /// Process transcript with a custom system prompt
 pub async fn process_with_prompt(
-    provider: &LlmProvider,
-    api_key: &str,
-    model: &str,
+    targets: &[LlmTarget],
     transcript: &str,
     prompt: &str,
-) -> Result<String, String> {
+    on_partial: Option<PartialCallback<'_>>,
+) -> Result<ChatOutcome, String> {
     if transcript.trim().is_empty() || prompt.trim().is_empty() {
-        return Ok(transcript.to_string());
+        return Ok(ChatOutcome::unchanged(transcript));
     }
 
-    call_llm_chat(provider, api_key, model, prompt, transcript).await
+    chat_with_fallback(targets, prompt, transcript, on_partial).await
 }
 
 /// System prompt for the meta-prompt generator
@@ -441,7 +937,9 @@ pub async fn validate_openai_key(api_key: &str) -> Result<(), String> {
             content: "Hi".to_string(),
         }],
         temperature: 0.0,
-        max_completion_tokens: 1,
+        max_completion_tokens: Some(1),
+        max_tokens: None,
+        stream: None,
     };
 
     let client = reqwest::Client::builder()
@@ -646,6 +1144,34 @@ async fn list_openai_models(api_key: &str) -> Result<Vec<ModelInfo>, String> {
     Ok(models)
 }
 
+/// Every model a self-hosted server lists is assumed to be usable for chat
+async fn list_custom_models(base_url: &str, api_key: &str) -> Result<Vec<ModelInfo>, String> {
+    let url = format!("{}/v1/models", normalize_base_url(base_url));
+    let mut builder = models_client()?.get(&url);
+    if !api_key.trim().is_empty() {
+        builder = builder.header("Authorization", format!("Bearer {}", api_key));
+    }
+    let response = builder
+        .send()
+        .await
+        .map_err(|e| format!("Custom endpoint models request failed: {}", e))?;
+
+    let body = read_success_body(response, "Custom endpoint").await?;
+    let result: OpenAiModelsResponse = serde_json::from_str(&body)
+        .map_err(|e| format!("Failed to parse custom endpoint models response: {}", e))?;
+
+    let mut models: Vec<ModelInfo> = result
+        .data
+        .into_iter()
+        .map(|m| ModelInfo {
+            display_name: m.id.clone(),
+            id: m.id,
+        })
+        .collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
+
 async fn list_google_models(api_key: &str) -> Result<Vec<ModelInfo>, String> {
     let url = format!("{}?key={}&pageSize=1000", GOOGLE_API_BASE, api_key);
     let response = models_client()?
@@ -721,6 +1247,7 @@ pub async fn list_models(
         LlmProvider::OpenAI => list_openai_models(api_key).await,
         LlmProvider::Google => list_google_models(api_key).await,
         LlmProvider::Anthropic => list_anthropic_models(api_key).await,
+        LlmProvider::Custom { base_url } => list_custom_models(base_url, api_key).await,
     }
 }
 
@@ -738,6 +1265,7 @@ pub async fn validate_anthropic_key(api_key: &str) -> Result<(), String> {
             role: "user".to_string(),
             content: "Hi".to_string(),
         }],
+        stream: None,
     };
 
     let client = reqwest::Client::builder()
@@ -770,3 +1298,87 @@ pub async fn validate_anthropic_key(api_key: &str) -> Result<(), String> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_and_request_errors_stop_the_fallback() {
+        assert!(!is_retryable_error(
+            "OpenAI API error 401 Unauthorized: invalid key"
+        ));
+        assert!(!is_retryable_error(
+            "Anthropic API error 400 Bad Request: overloaded"
+        ));
+        assert!(!is_retryable_error(
+            "Gemini API error 403 Forbidden: timeout"
+        ));
+        assert!(!is_retryable_error("Failed to parse LLM response: EOF"));
+    }
+
+    #[test]
+    fn timeouts_rate_limits_and_server_errors_fall_through() {
+        assert!(is_retryable_error("OpenAI API error 408 Request Timeout: "));
+        assert!(is_retryable_error(
+            "Gemini API error 429 Too Many Requests: {}"
+        ));
+        assert!(is_retryable_error(
+            "Custom endpoint API error 500 Internal Server Error: "
+        ));
+        assert!(is_retryable_error(
+            "Anthropic API error 529 <unknown status code>: "
+        ));
+    }
+
+    #[test]
+    fn mid_stream_overload_falls_through() {
+        assert!(is_retryable_error(
+            r#"Anthropic API error: {"type":"overloaded_error","message":"Overloaded"}"#
+        ));
+        assert!(is_retryable_error(
+            r#"Gemini API error: {"status":"RESOURCE_EXHAUSTED"}"#
+        ));
+    }
+
+    #[test]
+    fn network_errors_fall_through() {
+        assert!(is_retryable_error(
+            "LLM request failed: error sending request for url (http://localhost:11434/v1)"
+        ));
+        assert!(is_retryable_error(
+            "Anthropic request failed: operation timed out"
+        ));
+        assert!(is_retryable_error(
+            "Gemini request failed: connection refused"
+        ));
+    }
+
+    #[test]
+    fn interrupted_streams_fall_through() {
+        assert!(is_retryable_error(
+            "LLM stream interrupted: error decoding response body"
+        ));
+    }
+
+    #[test]
+    fn connection_words_alone_do_not_fall_through() {
+        assert!(!is_retryable_error(
+            "LLM request failed: builder error: relative URL without a base"
+        ));
+        assert!(!is_retryable_error(
+            "Failed to parse LLM response: missing field `connection`"
+        ));
+    }
+
+    #[test]
+    fn api_error_status_reads_the_code_after_the_provider() {
+        assert_eq!(
+            api_error_status("OpenAI API error 429 Too Many Requests: {}"),
+            Some(429)
+        );
+        assert_eq!(api_error_status("API error 503: busy"), Some(503));
+        assert_eq!(api_error_status("Anthropic API error: overloaded"), None);
+        assert_eq!(api_error_status("Network error: timeout"), None);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::dictionary;
+use regex::{NoExpand, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// A find-and-replace rule applied in Rust, without an LLM round trip
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalRule {
+    pub id: String,
+    pub find: String,
+    /// Replacement text; for regex rules `$1` / `${name}` refer to capture groups
+    #[serde(default)]
+    pub replace: String,
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    /// Only match whole words, so "cat" doesn't rewrite "category"
+    #[serde(default)]
+    pub whole_word: bool,
+    pub enabled: bool,
+}
+
+impl LocalRule {
+    fn compile(&self) -> Result<Regex, String> {
+        let pattern = if self.is_regex {
+            if self.whole_word {
+                format!(r"\b(?:{})\b", self.find)
+            } else {
+                self.find.clone()
+            }
+        } else if self.whole_word {
+            dictionary::whole_word_pattern(&self.find)
+        } else {
+            regex::escape(&self.find)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid pattern in rule '{}': {}", self.find, e))
+    }
+}
+
+/// Check every rule compiles, so a bad regex is reported when saving
+/// rather than silently skipped at dictation time
+pub fn validate(rules: &[LocalRule]) -> Result<(), String> {
+    for rule in rules {
+        if rule.find.is_empty() {
+            return Err("Rule pattern cannot be empty".to_string());
+        }
+        rule.compile()?;
+    }
+    Ok(())
+}
+
+/// The enabled rules compiled once, for applying them to many pieces of text
+/// such as every segment of a file transcript. Rules that fail to compile are
+/// skipped.
+pub struct RuleSet {
+    compiled: Vec<(Regex, LocalRule)>,
+}
+
+impl RuleSet {
+    pub fn new(rules: &[LocalRule]) -> Self {
+        let compiled = rules
+            .iter()
+            .filter(|r| r.enabled && !r.find.is_empty())
+            .filter_map(|rule| match rule.compile() {
+                Ok(re) => Some((re, rule.clone())),
+                Err(e) => {
+                    eprintln!("[LocalRules] Skipping rule: {}", e);
+                    None
+                }
+            })
+            .collect();
+        Self { compiled }
+    }
+
+    /// Apply the rules in order. Returns the rewritten text and how many
+    /// rules changed it.
+    pub fn apply(&self, text: &str) -> (String, usize) {
+        let mut result = text.to_string();
+        let mut changed = 0;
+        for (re, rule) in &self.compiled {
+            let replaced = if rule.is_regex {
+                re.replace_all(&result, rule.replace.as_str())
+            } else {
+                re.replace_all(&result, NoExpand(&rule.replace))
+            };
+            if replaced != result {
+                result = replaced.into_owned();
+                changed += 1;
+            }
+        }
+        (result, changed)
+    }
+}
+
+/// Apply the enabled rules in order. Rules that fail to compile are skipped.
+/// Returns the rewritten text and how many rules changed it.
+pub fn apply(text: &str, rules: &[LocalRule]) -> (String, usize) {
+    RuleSet::new(rules).apply(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(find: &str, replace: &str) -> LocalRule {
+        LocalRule {
+            id: find.to_string(),
+            find: find.to_string(),
+            replace: replace.to_string(),
+            is_regex: false,
+            case_sensitive: false,
+            whole_word: false,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn literal_rules_ignore_case_and_replace_verbatim() {
+        let rules = RuleSet::new(&[rule("gonna", "going to"), rule("price", "$5")]);
+        assert_eq!(
+            rules.apply("Gonna check the price"),
+            ("going to check the $5".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn whole_word_rules_leave_longer_words_alone() {
+        let mut cat = rule("cat", "dog");
+        cat.whole_word = true;
+        let mut cpp = rule("C++", "C plus plus");
+        cpp.whole_word = true;
+        let rules = RuleSet::new(&[cat, cpp]);
+        assert_eq!(
+            rules.apply("a cat in the category writes C++"),
+            ("a dog in the category writes C plus plus".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn regex_rules_expand_capture_groups() {
+        let mut date = rule(r"(\d+)/(\d+)", "$2.$1");
+        date.is_regex = true;
+        assert_eq!(
+            RuleSet::new(&[date]).apply("due 12/31"),
+            ("due 31.12".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn case_sensitive_rules_only_match_exact_case() {
+        let mut api = rule("API", "interface");
+        api.case_sensitive = true;
+        assert_eq!(
+            RuleSet::new(&[api]).apply("the api and the API"),
+            ("the api and the interface".to_string(), 1)
+        );
+    }
+
+    #[test]
+    fn disabled_and_invalid_rules_are_skipped() {
+        let mut disabled = rule("hello", "bye");
+        disabled.enabled = false;
+        let mut invalid = rule("(", "x");
+        invalid.is_regex = true;
+        let rules = [disabled, invalid, rule("world", "there")];
+        assert_eq!(
+            RuleSet::new(&rules).apply("hello world"),
+            ("hello there".to_string(), 1)
+        );
+        assert!(validate(&rules).is_err());
+    }
+
+    #[test]
+    fn unchanged_text_counts_no_rules() {
+        let rules = RuleSet::new(&[rule("missing", "found")]);
+        assert_eq!(rules.apply("nothing here"), ("nothing here".to_string(), 0));
+    }
+}
@@ -0,0 +1,420 @@
+use crate::stt::TimedTranscript;
+use crate::subtitles::{Segment, Word};
+use crate::wav::create_wav_header;
+use reqwest::multipart::{Form, Part};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT_SECS: u64 = 120; // Increased timeout for large files
+const MAX_RETRIES: u32 = 3;
+const CHANNELS: u16 = 1;
+const BITS_PER_SAMPLE: u16 = 16;
+
+/// Prefix of errors for requests that never got a response (connection
+/// refused or dropped, DNS failure, timeout)
+const NETWORK_ERROR_PREFIX: &str = "Network error";
+
+/// A server implementing OpenAI's `/v1/audio/transcriptions` API
+/// (Groq, OpenAI, faster-whisper-server, whisper.cpp server, ...)
+#[derive(Debug, Clone)]
+pub struct TranscriptionEndpoint {
+    /// Root URL; a trailing `/v1` is accepted and stripped
+    pub base_url: String,
+    /// Sent as a bearer token when non-empty
+    pub api_key: String,
+    pub model: String,
+    /// Provider name used in logs and error messages
+    pub label: String,
+}
+
+impl TranscriptionEndpoint {
+    fn url(&self) -> String {
+        format!(
+            "{}/v1/audio/transcriptions",
+            crate::llm::normalize_base_url(&self.base_url)
+        )
+    }
+
+    fn post(&self, client: &reqwest::Client, form: Form) -> reqwest::RequestBuilder {
+        let builder = client.post(self.url()).multipart(form);
+        if self.api_key.trim().is_empty() {
+            builder
+        } else {
+            builder.header("Authorization", format!("Bearer {}", self.api_key))
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+struct TranscriptionResponse {
+    text: String,
+}
+
+/// `response_format=verbose_json`, which adds timed segments and words
+#[derive(Deserialize, Default)]
+struct VerboseTranscriptionResponse {
+    text: String,
+    #[serde(default)]
+    segments: Vec<VerboseSegment>,
+    #[serde(default)]
+    words: Vec<VerboseWord>,
+}
+
+#[derive(Deserialize)]
+struct VerboseWord {
+    word: String,
+    start: f64,
+    end: f64,
+}
+
+#[derive(Deserialize)]
+struct VerboseSegment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+fn build_form(
+    endpoint: &TranscriptionEndpoint,
+    part: Part,
+    language: &str,
+    prompt: Option<&str>,
+    response_format: &str,
+) -> Form {
+    let mut form = Form::new()
+        .part("file", part)
+        .text("model", endpoint.model.clone())
+        .text("response_format", response_format.to_string());
+
+    // Ask for word and segment timings. Servers or models without
+    // verbose_json reject the request; see `transcribe_file_timed`.
+    if response_format == "verbose_json" {
+        form = form
+            .text("timestamp_granularities[]", "word")
+            .text("timestamp_granularities[]", "segment");
+    }
+
+    // Only include language if not auto-detect (empty or "auto" means auto-detect)
+    if !language.is_empty() && language != "auto" {
+        form = form.text("language", language.to_string());
+    }
+
+    // Vocabulary hint; the model imitates the prompt's spelling
+    if let Some(prompt) = prompt {
+        form = form.text("prompt", prompt.to_string());
+    }
+    form
+}
+
+fn request_error(e: reqwest::Error) -> String {
+    if e.is_connect() || e.is_timeout() {
+        format!("{}: {}", NETWORK_ERROR_PREFIX, e)
+    } else {
+        format!("Request failed: {}", e)
+    }
+}
+
+/// Transcription failures that may go away by themselves: rate limits,
+/// server errors, and requests that couldn't reach the server or timed out.
+/// Anything else (bad key, rejected audio, unparseable response) needs the
+/// user and is not worth retrying.
+pub fn is_transient_error(error: &str) -> bool {
+    if let Some(status) = crate::llm::api_error_status(error) {
+        return status == 408 || status == 429 || status >= 500;
+    }
+    error.starts_with(NETWORK_ERROR_PREFIX)
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| format!("Failed to create client: {}", e))
+}
+
+/// Transcribe raw PCM16 LE mono audio, wrapped in a WAV container for upload
+pub async fn transcribe_pcm16(
+    endpoint: &TranscriptionEndpoint,
+    audio_data: Vec<u8>,
+    sample_rate: u32,
+    language: &str,
+    prompt: Option<&str>,
+) -> Result<String, String> {
+    if audio_data.is_empty() {
+        return Ok(String::new());
+    }
+
+    let wav_header = create_wav_header(
+        audio_data.len() as u32,
+        sample_rate,
+        CHANNELS,
+        BITS_PER_SAMPLE,
+    );
+    let mut wav_data = wav_header;
+    wav_data.extend(audio_data);
+
+    let part = Part::bytes(wav_data)
+        .file_name("audio.wav")
+        .mime_str("audio/wav")
+        .map_err(|e| e.to_string())?;
+
+    let response = endpoint
+        .post(
+            &client()?,
+            build_form(endpoint, part, language, prompt, "json"),
+        )
+        .send()
+        .await
+        .map_err(request_error)?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("{} API error {}: {}", endpoint.label, status, body));
+    }
+
+    let result: TranscriptionResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+    Ok(result.text)
+}
+
+/// Get MIME type for audio file extension
+fn get_mime_type(extension: &str) -> &'static str {
+    match extension {
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "webm" => "audio/webm",
+        _ => "audio/mpeg", // Default to mp3
+    }
+}
+
+/// Transcribe audio from a file path
+/// Supports: mp3, wav, m4a, ogg, flac, webm
+pub async fn transcribe_file(
+    endpoint: &TranscriptionEndpoint,
+    file_path: &Path,
+    language: &str,
+    prompt: Option<&str>,
+) -> Result<String, String> {
+    let response: TranscriptionResponse =
+        request_file(endpoint, file_path, language, prompt, "json").await?;
+    Ok(response.text)
+}
+
+/// Whether a failed `verbose_json` request was refused for its format rather
+/// than for auth, rate limits or a server problem
+fn rejected_verbose_json(error: &str) -> bool {
+    crate::llm::api_error_status(error).is_some_and(|status| {
+        (400..500).contains(&status) && ![401, 403, 408, 429].contains(&status)
+    })
+}
+
+/// Transcribe a file with segment and word timestamps. Only servers that
+/// support `verbose_json` (Groq, OpenAI's whisper-1, faster-whisper-server)
+/// return them; others (gpt-4o-transcribe, some whisper.cpp and LM Studio
+/// builds) get a plain request and an untimed transcript. The API has no
+/// per-word confidence.
+pub async fn transcribe_file_timed(
+    endpoint: &TranscriptionEndpoint,
+    file_path: &Path,
+    language: &str,
+    prompt: Option<&str>,
+) -> Result<TimedTranscript, String> {
+    let response: VerboseTranscriptionResponse =
+        match request_file(endpoint, file_path, language, prompt, "verbose_json").await {
+            Ok(response) => response,
+            Err(e) if rejected_verbose_json(&e) => {
+                println!(
+                    "[Transcribe] {} rejected verbose_json, retrying without timestamps",
+                    endpoint.label
+                );
+                let text = transcribe_file(endpoint, file_path, language, prompt).await?;
+                return Ok(TimedTranscript {
+                    text,
+                    ..TimedTranscript::default()
+                });
+            }
+            Err(e) => return Err(e),
+        };
+    let segments = response
+        .segments
+        .into_iter()
+        .map(|s| Segment {
+            start: s.start,
+            end: s.end,
+            text: s.text.trim().to_string(),
+        })
+        .collect();
+    let words = response
+        .words
+        .into_iter()
+        .map(|w| Word {
+            start: w.start,
+            end: w.end,
+            text: w.word.trim().to_string(),
+            probability: None,
+        })
+        .collect();
+    Ok(TimedTranscript {
+        text: response.text,
+        segments,
+        words,
+    })
+}
+
+/// Upload a file with retries and parse the response. An empty file yields
+/// the default response without a request.
+async fn request_file<T: DeserializeOwned + Default>(
+    endpoint: &TranscriptionEndpoint,
+    file_path: &Path,
+    language: &str,
+    prompt: Option<&str>,
+    response_format: &str,
+) -> Result<T, String> {
+    // Get file name and extension for mime type (before reading file)
+    let file_name = file_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("audio.mp3")
+        .to_string();
+
+    let extension = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("mp3")
+        .to_lowercase();
+
+    let mime_type = get_mime_type(&extension);
+
+    println!(
+        "[Transcribe] MIME type: {}, File name: {}",
+        mime_type, file_name
+    );
+
+    let client = client()?;
+
+    // Retry logic - read file lazily in each attempt to avoid holding large data in memory
+    // and to avoid cloning large buffers on retry
+    let mut last_error = String::new();
+    for attempt in 1..=MAX_RETRIES {
+        println!(
+            "[Transcribe] Attempt {}/{} - Reading file: {:?}",
+            attempt, MAX_RETRIES, file_path
+        );
+
+        // Read file fresh for each attempt (avoids cloning large buffers)
+        // The OS file cache ensures this is efficient on retries
+        let file_data =
+            std::fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+        if file_data.is_empty() {
+            println!("[Transcribe] File is empty");
+            return Ok(T::default());
+        }
+
+        if attempt == 1 {
+            let file_size_mb = file_data.len() as f64 / (1024.0 * 1024.0);
+            println!("[Transcribe] File size: {:.2} MB", file_size_mb);
+        }
+
+        println!("[Transcribe] Sending to {} API...", endpoint.label);
+
+        let part = Part::bytes(file_data)
+            .file_name(file_name.clone())
+            .mime_str(mime_type)
+            .map_err(|e| e.to_string())?;
+
+        match endpoint
+            .post(
+                &client,
+                build_form(endpoint, part, language, prompt, response_format),
+            )
+            .send()
+            .await
+        {
+            Ok(response) => {
+                if response.status().is_success() {
+                    println!("[Transcribe] Request successful!");
+                    return response
+                        .json::<T>()
+                        .await
+                        .map_err(|e| format!("Failed to parse response: {}", e));
+                } else {
+                    let status = response.status();
+                    let body = response.text().await.unwrap_or_default();
+                    last_error = format!("{} API error {}: {}", endpoint.label, status, body);
+                    println!("[Transcribe] API error: {}", last_error);
+                    // A rejected request fails the same way every time
+                    if !is_transient_error(&last_error) {
+                        break;
+                    }
+                }
+            }
+            Err(e) => {
+                last_error = request_error(e);
+                println!("[Transcribe] Request error: {}", last_error);
+            }
+        }
+
+        // Wait before retry (exponential backoff)
+        if attempt < MAX_RETRIES {
+            let wait_secs = 2u64.pow(attempt);
+            println!("[Transcribe] Waiting {}s before retry...", wait_secs);
+            tokio::time::sleep(Duration::from_secs(wait_secs)).await;
+        }
+    }
+
+    Err(last_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transient_errors_are_rate_limits_server_errors_and_network_failures() {
+        assert!(is_transient_error(
+            "Groq API error 429 Too Many Requests: {}"
+        ));
+        assert!(is_transient_error(
+            "Custom API error 503 Service Unavailable: "
+        ));
+        assert!(is_transient_error("Groq API error 408 Request Timeout: "));
+        assert!(is_transient_error(
+            "Network error: error sending request for url"
+        ));
+    }
+
+    #[test]
+    fn errors_needing_the_user_are_not_transient() {
+        assert!(!is_transient_error(
+            "Groq API error 401 Unauthorized: invalid key"
+        ));
+        assert!(!is_transient_error(
+            "Groq API error 413 Payload Too Large: connection"
+        ));
+        assert!(!is_transient_error("Request failed: builder error"));
+        assert!(!is_transient_error("Failed to parse response: timeout"));
+        assert!(!is_transient_error("Failed to read file: connection reset"));
+    }
+
+    #[test]
+    fn format_rejections_fall_back_to_untimed_json() {
+        assert!(rejected_verbose_json(
+            "Custom STT API error 400 Bad Request: response_format 'verbose_json' is not compatible"
+        ));
+        assert!(rejected_verbose_json("Custom STT API error 422: "));
+        assert!(!rejected_verbose_json("Custom STT API error 401: "));
+        assert!(!rejected_verbose_json("Custom STT API error 429: "));
+        assert!(!rejected_verbose_json("Custom STT API error 500: "));
+        assert!(!rejected_verbose_json("Network error: connection refused"));
+    }
+}
@@ -1,5 +1,8 @@
-use parakeet_rs::{ParakeetTDT, Transcriber};
-use rubato::{FftFixedIn, Resampler};
+use crate::chunking::{self, WindowProgress};
+use crate::decode;
+use crate::stt::TimedTranscript;
+use crate::subtitles::{self, Word};
+use parakeet_rs::{ParakeetTDT, TimestampMode, Transcriber};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
@@ -44,24 +47,6 @@ const MODEL_FILES: &[(&str, &str, &str, bool)] = &[
 pub const EVENT_DOWNLOAD_PROGRESS: &str = "parakeet-download-progress";
 pub const EVENT_LOADING: &str = "parakeet-loading";
 
-/// STT provider for speech-to-text
-#[derive(Debug, Clone, PartialEq)]
-pub enum SttProvider {
-    Groq,
-    Parakeet,
-    Whisper,
-}
-
-impl SttProvider {
-    pub fn from_store_value(s: &str) -> Self {
-        match s {
-            "parakeet" => Self::Parakeet,
-            "whisper" => Self::Whisper,
-            _ => Self::Groq,
-        }
-    }
-}
-
 /// Flag to prevent model deletion during active transcription.
 static IS_TRANSCRIBING: AtomicBool = AtomicBool::new(false);
 
@@ -252,6 +237,22 @@ pub fn load_model(state: &ParakeetState, model_dir: &Path) -> Result<(), String>
     Ok(())
 }
 
+/// Load the downloaded model unless it is loaded already, emitting the
+/// loading events around it. Blocks for as long as loading takes.
+pub fn ensure_model_loaded(app: &AppHandle, state: &ParakeetState) -> Result<(), String> {
+    if is_model_loaded(state) {
+        return Ok(());
+    }
+    let model_dir = get_model_dir(app)?;
+    if !is_model_downloaded(&model_dir) {
+        return Err("Parakeet model not downloaded. Download it in Settings.".to_string());
+    }
+    app.emit(EVENT_LOADING, true).ok();
+    let result = load_model(state, &model_dir);
+    app.emit(EVENT_LOADING, false).ok();
+    result
+}
+
 pub fn unload_model(state: &ParakeetState) -> Result<(), String> {
     let mut model_guard = state.lock_model();
     *model_guard = None;
@@ -289,7 +290,7 @@ pub fn transcribe_pcm16(state: &ParakeetState, pcm16_24khz: Vec<u8>) -> Result<S
     samples.extend(std::iter::repeat(0.0f32).take(silence_frames));
 
     // Resample from 24kHz to 16kHz (Parakeet expects 16kHz)
-    let samples = resample_audio(&samples, INPUT_SAMPLE_RATE, PARAKEET_SAMPLE_RATE)?;
+    let samples = decode::resample(&samples, INPUT_SAMPLE_RATE, PARAKEET_SAMPLE_RATE)?;
 
     println!(
         "[Parakeet] Transcribing {} samples at {}Hz",
@@ -304,7 +305,14 @@ pub fn transcribe_pcm16(state: &ParakeetState, pcm16_24khz: Vec<u8>) -> Result<S
     Ok(result.text)
 }
 
-pub fn transcribe_file_local(state: &ParakeetState, file_path: &Path) -> Result<String, String> {
+/// Transcribe any supported audio or video file with word timestamps,
+/// grouped into segments for subtitles. Long files are decoded and
+/// transcribed in windows. parakeet-rs doesn't expose token confidence.
+pub fn transcribe_file_local(
+    state: &ParakeetState,
+    file_path: &Path,
+    progress: Option<&WindowProgress>,
+) -> Result<TimedTranscript, String> {
     let mut model_guard = state.lock_model();
     let model = model_guard
         .as_mut()
@@ -312,61 +320,41 @@ pub fn transcribe_file_local(state: &ParakeetState, file_path: &Path) -> Result<
 
     println!("[Parakeet] Transcribing file: {:?}", file_path);
 
-    let result = model
-        .transcribe_file(file_path, None)
-        .map_err(|e| format!("File transcription failed: {}", e))?;
-
-    Ok(result.text)
+    // Decoded in-process; parakeet-rs itself only reads WAV
+    chunking::transcribe_windowed(file_path, progress, |samples| {
+        transcribe_window(model, samples)
+    })
 }
 
-/// Resample audio using FFT-based resampling with proper anti-aliasing.
-/// Processes in fixed-size chunks so the internal resampler state carries over
-/// and no tail audio is lost.
-fn resample_audio(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>, String> {
-    if from_rate == to_rate || samples.is_empty() {
-        return Ok(samples.to_vec());
+fn transcribe_window(model: &mut ParakeetTDT, samples: &[f32]) -> Result<TimedTranscript, String> {
+    if samples.is_empty() {
+        return Ok(TimedTranscript::default());
     }
+    let silence_frames = (PARAKEET_SAMPLE_RATE as f32 * SILENCE_PADDING_SECS) as usize;
+    let mut samples = samples.to_vec();
+    samples.extend(std::iter::repeat(0.0f32).take(silence_frames));
 
-    let chunk_size = 1024;
-    let mut resampler = FftFixedIn::<f32>::new(
-        from_rate as usize,
-        to_rate as usize,
-        chunk_size,
-        1, // sub_chunks
-        1, // channels
-    )
-    .map_err(|e| format!("Failed to create resampler: {}", e))?;
-
-    let mut output = Vec::with_capacity(samples.len() * to_rate as usize / from_rate as usize);
-    let mut pos = 0;
-
-    while pos < samples.len() {
-        let frames_needed = resampler.input_frames_next();
-        let end = (pos + frames_needed).min(samples.len());
-        let mut chunk = samples[pos..end].to_vec();
-        chunk.resize(frames_needed, 0.0); // zero-pad final chunk
-
-        let resampled = resampler
-            .process(&[chunk], None)
-            .map_err(|e| format!("Resampling failed: {}", e))?;
-
-        if let Some(channel) = resampled.into_iter().next() {
-            output.extend(channel);
-        }
-
-        pos += frames_needed;
-    }
+    let result = model
+        .transcribe_samples(samples, PARAKEET_SAMPLE_RATE, 1, Some(TimestampMode::Words))
+        .map_err(|e| format!("File transcription failed: {}", e))?;
 
-    // Flush: one extra zero-padded chunk to push remaining samples through the filter
-    let frames_needed = resampler.input_frames_next();
-    let flush = vec![0.0f32; frames_needed];
-    if let Ok(resampled) = resampler.process(&[flush], None) {
-        if let Some(channel) = resampled.into_iter().next() {
-            output.extend(channel);
-        }
-    }
+    let words: Vec<Word> = result
+        .tokens
+        .iter()
+        .filter(|token| !token.text.trim().is_empty())
+        .map(|token| Word {
+            start: token.start as f64,
+            end: token.end as f64,
+            text: token.text.trim().to_string(),
+            probability: None,
+        })
+        .collect();
 
-    Ok(output)
+    Ok(TimedTranscript {
+        text: result.text,
+        segments: subtitles::segments_from_words(&words),
+        words,
+    })
 }
 
 pub fn delete_model(model_dir: &Path) -> Result<(), String> {
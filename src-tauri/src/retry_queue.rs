@@ -0,0 +1,340 @@
+use crate::wav::WavWriter;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const QUEUE_DIR_NAME: &str = "retry_queue";
+
+/// First automatic retry after 30s, doubling up to 30 minutes
+const RETRY_BASE_DELAY_MS: i64 = 30_000;
+const RETRY_MAX_DELAY_MS: i64 = 30 * 60 * 1000;
+
+/// After this many failed attempts a recording is only retried manually
+pub const MAX_AUTO_ATTEMPTS: u32 = 10;
+
+static ID_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// A dictation whose cloud transcription failed, saved as `<id>.wav` with
+/// this metadata in `<id>.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedRecording {
+    pub id: String,
+    /// Unix timestamp in milliseconds
+    pub created_at: i64,
+    /// STT provider store value the recording was made with
+    pub provider: String,
+    pub engine: String,
+    pub language: String,
+    pub duration_seconds: f64,
+    /// Mode id (or "none"/"rules") the dictation was recorded with; missing
+    /// for recordings queued before it was stored
+    #[serde(default)]
+    pub mode_id: Option<String>,
+    /// Failed transcription attempts, including the original one
+    pub attempts: u32,
+    pub last_error: String,
+    /// Unix timestamp in milliseconds of the next automatic retry
+    pub next_attempt_at: i64,
+}
+
+impl QueuedRecording {
+    pub fn is_due(&self) -> bool {
+        self.attempts < MAX_AUTO_ATTEMPTS && self.next_attempt_at <= now_ms()
+    }
+
+    fn record_failure(&mut self, error: &str) {
+        self.attempts += 1;
+        self.last_error = error.to_string();
+        let backoff = RETRY_BASE_DELAY_MS
+            .saturating_mul(1i64 << (self.attempts - 1).min(16))
+            .min(RETRY_MAX_DELAY_MS);
+        self.next_attempt_at = now_ms() + backoff;
+    }
+}
+
+/// Metadata for a recording that is about to be queued
+pub struct NewQueuedRecording {
+    pub provider: String,
+    pub engine: String,
+    pub language: String,
+    pub duration_seconds: f64,
+    pub mode_id: String,
+    pub error: String,
+}
+
+#[derive(Clone, Default)]
+pub struct RetryQueue {
+    dir: Arc<Mutex<Option<PathBuf>>>,
+}
+
+impl RetryQueue {
+    /// Use (and create) the queue directory inside the app data directory
+    pub fn open(&self, app_data_dir: &Path) -> Result<(), String> {
+        let dir = app_data_dir.join(QUEUE_DIR_NAME);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create retry queue dir: {}", e))?;
+        *self
+            .dir
+            .lock()
+            .map_err(|e| format!("Retry queue lock poisoned: {}", e))? = Some(dir.clone());
+
+        let pending = self.list().map(|items| items.len()).unwrap_or(0);
+        println!("[RetryQueue] Opened at {:?} ({} pending)", dir, pending);
+        Ok(())
+    }
+
+    fn dir(&self) -> Result<PathBuf, String> {
+        self.dir
+            .lock()
+            .map_err(|e| format!("Retry queue lock poisoned: {}", e))?
+            .clone()
+            .ok_or_else(|| "Retry queue is not available".to_string())
+    }
+
+    /// Ids come back from the frontend, so make sure they can't escape the queue dir
+    fn item_path(&self, id: &str, extension: &str) -> Result<PathBuf, String> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid queued recording id: {}", id));
+        }
+        Ok(self.dir()?.join(format!("{}.{}", id, extension)))
+    }
+
+    pub fn wav_path(&self, id: &str) -> Result<PathBuf, String> {
+        self.item_path(id, "wav")
+    }
+
+    fn new_item(&self, meta: NewQueuedRecording) -> QueuedRecording {
+        let created_at = now_ms();
+        let mut item = QueuedRecording {
+            id: format!(
+                "{}-{}",
+                created_at,
+                ID_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ),
+            created_at,
+            provider: meta.provider,
+            engine: meta.engine,
+            language: meta.language,
+            duration_seconds: meta.duration_seconds,
+            mode_id: Some(meta.mode_id),
+            attempts: 0,
+            last_error: String::new(),
+            next_attempt_at: created_at,
+        };
+        item.record_failure(&meta.error);
+        item
+    }
+
+    fn write_meta(&self, item: &QueuedRecording) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(item).map_err(|e| e.to_string())?;
+        std::fs::write(self.item_path(&item.id, "json")?, json)
+            .map_err(|e| format!("Failed to save queued recording: {}", e))
+    }
+
+    /// Queue in-memory PCM16 mono audio
+    pub fn enqueue_pcm16(
+        &self,
+        pcm16: &[u8],
+        sample_rate: u32,
+        meta: NewQueuedRecording,
+    ) -> Result<QueuedRecording, String> {
+        let item = self.new_item(meta);
+        let file = File::create(self.wav_path(&item.id)?)
+            .map_err(|e| format!("Failed to create queued recording: {}", e))?;
+        let mut writer = WavWriter::create(file, sample_rate)?;
+        writer.write_pcm16(pcm16)?;
+        writer.finish()?;
+        self.write_meta(&item)?;
+        println!(
+            "[RetryQueue] Queued {} ({:.1}s)",
+            item.id, item.duration_seconds
+        );
+        Ok(item)
+    }
+
+    /// Queue a recording that is already a WAV file on disk
+    pub fn enqueue_wav(
+        &self,
+        wav: &Path,
+        meta: NewQueuedRecording,
+    ) -> Result<QueuedRecording, String> {
+        let item = self.new_item(meta);
+        std::fs::copy(wav, self.wav_path(&item.id)?)
+            .map_err(|e| format!("Failed to copy queued recording: {}", e))?;
+        self.write_meta(&item)?;
+        println!(
+            "[RetryQueue] Queued {} ({:.1}s)",
+            item.id, item.duration_seconds
+        );
+        Ok(item)
+    }
+
+    /// Queued recordings, oldest first. Metadata without audio is cleaned up.
+    pub fn list(&self) -> Result<Vec<QueuedRecording>, String> {
+        let entries = std::fs::read_dir(self.dir()?)
+            .map_err(|e| format!("Failed to read retry queue: {}", e))?;
+
+        let mut items = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let item = match std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str::<QueuedRecording>(&json).ok())
+            {
+                Some(item) => item,
+                None => {
+                    println!("[RetryQueue] Skipping unreadable entry {:?}", path);
+                    continue;
+                }
+            };
+            if !self.wav_path(&item.id).map(|p| p.exists()).unwrap_or(false) {
+                println!("[RetryQueue] Removing {} (audio missing)", item.id);
+                std::fs::remove_file(&path).ok();
+                continue;
+            }
+            items.push(item);
+        }
+        items.sort_by_key(|item| item.created_at);
+        Ok(items)
+    }
+
+    pub fn get(&self, id: &str) -> Result<QueuedRecording, String> {
+        let json = std::fs::read_to_string(self.item_path(id, "json")?)
+            .map_err(|_| format!("Queued recording {} not found", id))?;
+        serde_json::from_str(&json).map_err(|e| format!("Corrupt queued recording {}: {}", id, e))
+    }
+
+    /// Count a failed retry and schedule the next one
+    pub fn record_failure(&self, id: &str, error: &str) -> Result<QueuedRecording, String> {
+        let mut item = self.get(id)?;
+        item.record_failure(error);
+        self.write_meta(&item)?;
+        Ok(item)
+    }
+
+    pub fn remove(&self, id: &str) -> Result<(), String> {
+        for extension in ["wav", "json"] {
+            let path = self.item_path(id, extension)?;
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to remove queued recording: {}", e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(error: &str) -> NewQueuedRecording {
+        NewQueuedRecording {
+            provider: "groq".to_string(),
+            engine: "Groq".to_string(),
+            language: "en".to_string(),
+            duration_seconds: 1.5,
+            mode_id: "none".to_string(),
+            error: error.to_string(),
+        }
+    }
+
+    fn open_queue(dir: &Path) -> RetryQueue {
+        let queue = RetryQueue::default();
+        queue.open(dir).unwrap();
+        queue
+    }
+
+    /// Delay before the next retry after `attempts` failures
+    fn backoff_after(attempts: u32) -> i64 {
+        let mut item = RetryQueue::default().new_item(meta("first"));
+        for _ in 1..attempts {
+            item.record_failure("again");
+        }
+        item.next_attempt_at - now_ms()
+    }
+
+    fn assert_near(actual: i64, expected: i64) {
+        assert!(
+            (actual - expected).abs() < 1000,
+            "expected ~{}ms, got {}ms",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_near(backoff_after(1), 30_000);
+        assert_near(backoff_after(2), 60_000);
+        assert_near(backoff_after(4), 240_000);
+        assert_near(backoff_after(7), RETRY_MAX_DELAY_MS);
+        assert_near(backoff_after(40), RETRY_MAX_DELAY_MS);
+    }
+
+    #[test]
+    fn automatic_retries_stop_after_the_attempt_limit() {
+        let mut item = RetryQueue::default().new_item(meta("offline"));
+        item.next_attempt_at = 0;
+        assert!(item.is_due());
+        item.attempts = MAX_AUTO_ATTEMPTS;
+        assert!(!item.is_due());
+    }
+
+    #[test]
+    fn ids_cannot_escape_the_queue_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = open_queue(dir.path());
+        assert!(queue.wav_path("1700000000000-3").is_ok());
+        assert!(queue.wav_path("../secrets").is_err());
+        assert!(queue.wav_path("").is_err());
+    }
+
+    #[test]
+    fn queued_recordings_survive_until_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = open_queue(dir.path());
+        let item = queue
+            .enqueue_pcm16(&[0, 0, 1, 0], 16000, meta("timed out"))
+            .unwrap();
+        assert_eq!(item.attempts, 1);
+        assert_eq!(item.last_error, "timed out");
+
+        let failed = queue.record_failure(&item.id, "still offline").unwrap();
+        assert_eq!(failed.attempts, 2);
+        let listed = queue.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].last_error, "still offline");
+        assert_eq!(listed[0].mode_id.as_deref(), Some("none"));
+
+        queue.remove(&item.id).unwrap();
+        assert!(queue.list().unwrap().is_empty());
+        assert!(queue.get(&item.id).is_err());
+    }
+
+    #[test]
+    fn metadata_without_audio_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = open_queue(dir.path());
+        let item = queue
+            .enqueue_pcm16(&[0, 0], 16000, meta("offline"))
+            .unwrap();
+        std::fs::remove_file(queue.wav_path(&item.id).unwrap()).unwrap();
+
+        assert!(queue.list().unwrap().is_empty());
+        assert!(queue.get(&item.id).is_err());
+    }
+}
@@ -0,0 +1,650 @@
+use crate::chunking::WindowProgress;
+use crate::dictionary::{self, DictionaryEntry};
+use crate::openai_stt::{self, TranscriptionEndpoint};
+use crate::subtitles::{self, Segment, Word};
+use crate::{audio, groq, parakeet, store_keys, transcribe, whisper};
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
+
+/// STT provider for speech-to-text
+#[derive(Debug, Clone, PartialEq)]
+pub enum SttProvider {
+    Groq,
+    Parakeet,
+    Whisper,
+    /// User-configured OpenAI-compatible transcription server
+    Custom,
+}
+
+impl SttProvider {
+    /// Read the selected provider, defaulting to Groq for unknown values
+    pub fn from_store_value(s: &str) -> Self {
+        Self::parse(s).unwrap_or(Self::Groq)
+    }
+
+    /// Strict variant of `from_store_value` for lists and overrides, where an
+    /// unknown value should be skipped rather than replaced by Groq
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "groq" => Some(Self::Groq),
+            "parakeet" => Some(Self::Parakeet),
+            "whisper" => Some(Self::Whisper),
+            "custom" => Some(Self::Custom),
+            _ => None,
+        }
+    }
+
+    pub fn as_store_value(&self) -> &'static str {
+        match self {
+            Self::Groq => "groq",
+            Self::Parakeet => "parakeet",
+            Self::Whisper => "whisper",
+            Self::Custom => "custom",
+        }
+    }
+
+    /// Transcribes over the network, so failures are often transient
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Self::Groq | Self::Custom)
+    }
+}
+
+/// Languages Parakeet TDT v3 was trained on. The model detects the spoken
+/// language on its own, so this is only used to flag unsupported selections.
+const PARAKEET_LANGUAGES: &[&str] = &[
+    "bg", "hr", "cs", "da", "nl", "en", "et", "fi", "fr", "de", "el", "hu", "it", "lv", "lt", "mt",
+    "pl", "pt", "ro", "sk", "sl", "es", "sv", "ru", "uk",
+];
+
+/// Per-request options shared by every engine
+#[derive(Clone)]
+pub struct TranscribeOptions {
+    /// ISO 639-1 code, or "auto" / empty for auto-detection
+    pub language: String,
+    /// Custom vocabulary. Promptable engines get it as a spelling hint,
+    /// the others apply it as a replacement pass on the output.
+    pub dictionary: Vec<DictionaryEntry>,
+    /// Progress of windowed file transcription on local engines
+    pub progress: Option<WindowProgress>,
+}
+
+impl TranscribeOptions {
+    fn prompt(&self) -> Option<String> {
+        dictionary::build_prompt(&self.dictionary)
+    }
+}
+
+/// File transcription with timestamps. `segments` and `words` are empty when
+/// the engine can't report them.
+#[derive(Debug, Clone, Default)]
+pub struct TimedTranscript {
+    pub text: String,
+    pub segments: Vec<Segment>,
+    pub words: Vec<Word>,
+}
+
+impl TimedTranscript {
+    /// Shift timestamps of a chunk to its position in the whole file
+    pub fn offset(&mut self, seconds: f64) {
+        subtitles::offset(&mut self.segments, seconds);
+        subtitles::offset_words(&mut self.words, seconds);
+    }
+}
+
+/// A speech-to-text backend. Implementations are registered in [`SttRegistry`]
+/// so commands never need to branch on the concrete provider.
+#[async_trait]
+pub trait SttEngine: Send + Sync {
+    fn provider(&self) -> SttProvider;
+
+    /// Human-readable name for logs and progress messages
+    fn display_name(&self) -> &'static str;
+
+    /// Whether the engine can transcribe right now. The error is a
+    /// user-facing message explaining what is missing.
+    fn check_ready(&self, app: &AppHandle) -> Result<(), String>;
+
+    fn supports_language(&self, language: &str) -> bool;
+
+    /// Whether `path` is too large for a single `transcribe_file` call and
+    /// has to be split first
+    fn needs_chunking(&self, _path: &Path) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    /// Transcribe raw PCM16 LE mono audio at 24kHz (the capture format)
+    async fn transcribe_pcm16(
+        &self,
+        app: &AppHandle,
+        pcm16_24khz: Vec<u8>,
+        options: &TranscribeOptions,
+    ) -> Result<String, String>;
+
+    async fn transcribe_file(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<String, String>;
+
+    /// Like `transcribe_file`, but with segment timestamps where the engine has them
+    async fn transcribe_file_timed(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<TimedTranscript, String> {
+        let text = self.transcribe_file(app, path, options).await?;
+        Ok(TimedTranscript {
+            text,
+            ..TimedTranscript::default()
+        })
+    }
+}
+
+/// Apply dictionary replacements to the text, segments and words of a
+/// transcript from an engine that can't be prompted
+fn apply_dictionary(transcript: TimedTranscript, options: &TranscribeOptions) -> TimedTranscript {
+    let replace = |text: &str| dictionary::apply_replacements(text, &options.dictionary);
+    TimedTranscript {
+        text: replace(&transcript.text),
+        segments: transcript
+            .segments
+            .into_iter()
+            .map(|segment| Segment {
+                text: replace(&segment.text),
+                ..segment
+            })
+            .collect(),
+        words: transcript
+            .words
+            .into_iter()
+            .map(|word| Word {
+                text: replace(&word.text),
+                ..word
+            })
+            .collect(),
+    }
+}
+
+/// Run a blocking local inference job off the async runtime. The transcribing
+/// flag stays set for the whole job so models can't be deleted mid-inference.
+async fn run_local<T, F>(job: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, String> + Send + 'static,
+{
+    parakeet::set_transcribing(true);
+    let result = tokio::task::spawn_blocking(job).await;
+    parakeet::set_transcribing(false);
+    result.map_err(|e| format!("Transcription task failed: {}", e))?
+}
+
+// ============== Groq ==============
+
+pub struct GroqEngine;
+
+impl GroqEngine {
+    fn api_key(app: &AppHandle) -> Result<String, String> {
+        crate::get_groq_api_key_from_store(app)
+            .filter(|k| !k.trim().is_empty())
+            .ok_or_else(|| "No API key configured. Add your Groq API key in Settings.".to_string())
+    }
+}
+
+#[async_trait]
+impl SttEngine for GroqEngine {
+    fn provider(&self) -> SttProvider {
+        SttProvider::Groq
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Groq"
+    }
+
+    fn check_ready(&self, app: &AppHandle) -> Result<(), String> {
+        Self::api_key(app).map(|_| ())
+    }
+
+    fn supports_language(&self, _language: &str) -> bool {
+        true
+    }
+
+    fn needs_chunking(&self, path: &Path) -> Result<bool, String> {
+        transcribe::needs_chunking(path)
+    }
+
+    async fn transcribe_pcm16(
+        &self,
+        app: &AppHandle,
+        pcm16_24khz: Vec<u8>,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        let api_key = Self::api_key(app)?;
+        let prompt = options.prompt();
+        groq::transcribe(&api_key, pcm16_24khz, &options.language, prompt.as_deref()).await
+    }
+
+    async fn transcribe_file(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        let api_key = Self::api_key(app)?;
+        let prompt = options.prompt();
+        groq::transcribe_file(&api_key, path, &options.language, prompt.as_deref()).await
+    }
+
+    async fn transcribe_file_timed(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<TimedTranscript, String> {
+        let api_key = Self::api_key(app)?;
+        let prompt = options.prompt();
+        groq::transcribe_file_timed(&api_key, path, &options.language, prompt.as_deref()).await
+    }
+}
+
+// ============== Parakeet ==============
+
+pub struct ParakeetEngine;
+
+#[async_trait]
+impl SttEngine for ParakeetEngine {
+    fn provider(&self) -> SttProvider {
+        SttProvider::Parakeet
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Parakeet"
+    }
+
+    /// A downloaded model that isn't loaded yet is loaded on first use
+    fn check_ready(&self, app: &AppHandle) -> Result<(), String> {
+        if parakeet::is_model_loaded(&app.state::<parakeet::ParakeetState>())
+            || parakeet::is_model_downloaded(&parakeet::get_model_dir(app)?)
+        {
+            Ok(())
+        } else {
+            Err("Parakeet model not downloaded. Download it in Settings.".to_string())
+        }
+    }
+
+    fn supports_language(&self, language: &str) -> bool {
+        language.is_empty() || language == "auto" || PARAKEET_LANGUAGES.contains(&language)
+    }
+
+    async fn transcribe_pcm16(
+        &self,
+        app: &AppHandle,
+        pcm16_24khz: Vec<u8>,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        let app = app.clone();
+        let state = app.state::<parakeet::ParakeetState>().inner().clone();
+        let text = run_local(move || {
+            parakeet::ensure_model_loaded(&app, &state)?;
+            parakeet::transcribe_pcm16(&state, pcm16_24khz)
+        })
+        .await?;
+        // Parakeet can't be prompted, so the dictionary is applied afterwards
+        Ok(dictionary::apply_replacements(&text, &options.dictionary))
+    }
+
+    async fn transcribe_file(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        Ok(self.transcribe_file_timed(app, path, options).await?.text)
+    }
+
+    async fn transcribe_file_timed(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<TimedTranscript, String> {
+        let app = app.clone();
+        let state = app.state::<parakeet::ParakeetState>().inner().clone();
+        let path = path.to_path_buf();
+        let progress = options.progress.clone();
+        let transcript = run_local(move || {
+            parakeet::ensure_model_loaded(&app, &state)?;
+            parakeet::transcribe_file_local(&state, &path, progress.as_ref())
+        })
+        .await?;
+        Ok(apply_dictionary(transcript, options))
+    }
+}
+
+// ============== Whisper ==============
+
+pub struct WhisperEngine;
+
+#[async_trait]
+impl SttEngine for WhisperEngine {
+    fn provider(&self) -> SttProvider {
+        SttProvider::Whisper
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Whisper"
+    }
+
+    /// A downloaded model that isn't loaded yet is loaded on first use
+    fn check_ready(&self, app: &AppHandle) -> Result<(), String> {
+        if whisper::is_model_loaded(&app.state::<whisper::WhisperState>())
+            || whisper::is_model_downloaded(&whisper::get_model_dir(app)?)
+        {
+            Ok(())
+        } else {
+            Err("Whisper model not downloaded. Download it in Settings.".to_string())
+        }
+    }
+
+    fn supports_language(&self, _language: &str) -> bool {
+        true
+    }
+
+    async fn transcribe_pcm16(
+        &self,
+        app: &AppHandle,
+        pcm16_24khz: Vec<u8>,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        let app = app.clone();
+        let state = app.state::<whisper::WhisperState>().inner().clone();
+        let language = options.language.clone();
+        let prompt = options.prompt();
+        run_local(move || {
+            whisper::ensure_model_loaded(&app, &state)?;
+            whisper::transcribe_pcm16(&state, pcm16_24khz, &language, prompt.as_deref())
+        })
+        .await
+    }
+
+    async fn transcribe_file(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        Ok(self.transcribe_file_timed(app, path, options).await?.text)
+    }
+
+    async fn transcribe_file_timed(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<TimedTranscript, String> {
+        let app = app.clone();
+        let state = app.state::<whisper::WhisperState>().inner().clone();
+        let path = path.to_path_buf();
+        let language = options.language.clone();
+        let prompt = options.prompt();
+        let progress = options.progress.clone();
+        run_local(move || {
+            whisper::ensure_model_loaded(&app, &state)?;
+            whisper::transcribe_file_local(
+                &state,
+                &path,
+                &language,
+                prompt.as_deref(),
+                progress.as_ref(),
+            )
+        })
+        .await
+    }
+}
+
+// ============== Custom (OpenAI-compatible) ==============
+
+pub struct CustomEngine;
+
+impl CustomEngine {
+    fn endpoint(app: &AppHandle) -> Result<TranscriptionEndpoint, String> {
+        let base_url = crate::get_store_string(app, store_keys::CUSTOM_STT_BASE_URL)
+            .filter(|url| !url.trim().is_empty())
+            .ok_or_else(|| {
+                "No transcription server configured. Set its URL in Settings.".to_string()
+            })?;
+        let model = crate::get_store_string(app, store_keys::CUSTOM_STT_MODEL)
+            .filter(|m| !m.trim().is_empty())
+            .ok_or_else(|| "No transcription model configured. Set it in Settings.".to_string())?;
+        Ok(TranscriptionEndpoint {
+            base_url,
+            api_key: crate::get_store_string(app, store_keys::CUSTOM_STT_API_KEY)
+                .unwrap_or_default(),
+            model,
+            label: "Custom STT".to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl SttEngine for CustomEngine {
+    fn provider(&self) -> SttProvider {
+        SttProvider::Custom
+    }
+
+    fn display_name(&self) -> &'static str {
+        "Custom endpoint"
+    }
+
+    fn check_ready(&self, app: &AppHandle) -> Result<(), String> {
+        Self::endpoint(app).map(|_| ())
+    }
+
+    fn supports_language(&self, _language: &str) -> bool {
+        true
+    }
+
+    /// Hosted APIs share OpenAI's 25MB upload cap; local servers don't mind smaller files
+    fn needs_chunking(&self, path: &Path) -> Result<bool, String> {
+        transcribe::needs_chunking(path)
+    }
+
+    async fn transcribe_pcm16(
+        &self,
+        app: &AppHandle,
+        pcm16_24khz: Vec<u8>,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        let endpoint = Self::endpoint(app)?;
+        let prompt = options.prompt();
+        openai_stt::transcribe_pcm16(
+            &endpoint,
+            pcm16_24khz,
+            audio::TARGET_SAMPLE_RATE,
+            &options.language,
+            prompt.as_deref(),
+        )
+        .await
+    }
+
+    async fn transcribe_file(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<String, String> {
+        let endpoint = Self::endpoint(app)?;
+        let prompt = options.prompt();
+        openai_stt::transcribe_file(&endpoint, path, &options.language, prompt.as_deref()).await
+    }
+
+    async fn transcribe_file_timed(
+        &self,
+        app: &AppHandle,
+        path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<TimedTranscript, String> {
+        let endpoint = Self::endpoint(app)?;
+        let prompt = options.prompt();
+        openai_stt::transcribe_file_timed(&endpoint, path, &options.language, prompt.as_deref())
+            .await
+    }
+}
+
+// ============== Registry ==============
+
+/// Engines available to the transcription commands, looked up by provider
+pub struct SttRegistry {
+    engines: Vec<Arc<dyn SttEngine>>,
+}
+
+impl Default for SttRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            engines: Vec::new(),
+        };
+        registry.register(Arc::new(GroqEngine));
+        registry.register(Arc::new(ParakeetEngine));
+        registry.register(Arc::new(WhisperEngine));
+        registry.register(Arc::new(CustomEngine));
+        registry
+    }
+}
+
+impl SttRegistry {
+    /// Add an engine, replacing any engine already registered for the same provider
+    pub fn register(&mut self, engine: Arc<dyn SttEngine>) {
+        let provider = engine.provider();
+        self.engines.retain(|e| e.provider() != provider);
+        self.engines.push(engine);
+    }
+
+    pub fn get(&self, provider: &SttProvider) -> Result<Arc<dyn SttEngine>, String> {
+        self.engines
+            .iter()
+            .find(|e| &e.provider() == provider)
+            .cloned()
+            .ok_or_else(|| format!("No STT engine registered for {:?}", provider))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stand-in engine that only identifies itself
+    struct FakeEngine {
+        provider: SttProvider,
+        name: &'static str,
+    }
+
+    #[async_trait]
+    impl SttEngine for FakeEngine {
+        fn provider(&self) -> SttProvider {
+            self.provider.clone()
+        }
+
+        fn display_name(&self) -> &'static str {
+            self.name
+        }
+
+        fn check_ready(&self, _app: &AppHandle) -> Result<(), String> {
+            Ok(())
+        }
+
+        fn supports_language(&self, _language: &str) -> bool {
+            true
+        }
+
+        async fn transcribe_pcm16(
+            &self,
+            _app: &AppHandle,
+            _pcm16_24khz: Vec<u8>,
+            _options: &TranscribeOptions,
+        ) -> Result<String, String> {
+            Ok(self.name.to_string())
+        }
+
+        async fn transcribe_file(
+            &self,
+            _app: &AppHandle,
+            _path: &Path,
+            _options: &TranscribeOptions,
+        ) -> Result<String, String> {
+            Ok(self.name.to_string())
+        }
+    }
+
+    fn fake(provider: SttProvider, name: &'static str) -> Arc<dyn SttEngine> {
+        Arc::new(FakeEngine { provider, name })
+    }
+
+    const PROVIDERS: [SttProvider; 4] = [
+        SttProvider::Groq,
+        SttProvider::Parakeet,
+        SttProvider::Whisper,
+        SttProvider::Custom,
+    ];
+
+    #[test]
+    fn store_values_round_trip() {
+        for provider in PROVIDERS {
+            assert_eq!(
+                SttProvider::parse(provider.as_store_value()),
+                Some(provider.clone())
+            );
+            assert_eq!(
+                SttProvider::from_store_value(provider.as_store_value()),
+                provider
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_values_are_skipped_by_parse_but_default_to_groq() {
+        for value in ["", "Parakeet", "openai", "deepgram"] {
+            assert_eq!(SttProvider::parse(value), None);
+            assert_eq!(SttProvider::from_store_value(value), SttProvider::Groq);
+        }
+    }
+
+    #[test]
+    fn default_registry_has_an_engine_for_every_provider() {
+        let registry = SttRegistry::default();
+        for provider in PROVIDERS {
+            assert_eq!(registry.get(&provider).unwrap().provider(), provider);
+        }
+    }
+
+    #[test]
+    fn register_replaces_the_engine_of_the_same_provider() {
+        let mut registry = SttRegistry::default();
+        registry.register(fake(SttProvider::Whisper, "Fake Whisper"));
+        assert_eq!(
+            registry.get(&SttProvider::Whisper).unwrap().display_name(),
+            "Fake Whisper"
+        );
+        assert_eq!(
+            registry.get(&SttProvider::Groq).unwrap().display_name(),
+            GroqEngine.display_name()
+        );
+        assert_eq!(registry.engines.len(), PROVIDERS.len());
+    }
+
+    #[test]
+    fn get_fails_for_unregistered_providers() {
+        let mut registry = SttRegistry {
+            engines: Vec::new(),
+        };
+        registry.register(fake(SttProvider::Custom, "Fake server"));
+        assert_eq!(
+            registry.get(&SttProvider::Custom).unwrap().display_name(),
+            "Fake server"
+        );
+        let error = registry.get(&SttProvider::Parakeet).err().unwrap();
+        assert_eq!(error, "No STT engine registered for Parakeet");
+    }
+}
@@ -0,0 +1,279 @@
+use serde::{Deserialize, Serialize};
+
+/// A stretch of transcript with its position in the source audio
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    /// Seconds from the start of the file
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// A single word with its timing and, where the engine reports it, how sure
+/// the engine was about it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Word {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// 0.0-1.0; `None` for engines that don't expose confidence
+    #[serde(default)]
+    pub probability: Option<f32>,
+}
+
+/// Pause between words that starts a new segment even mid-sentence
+const SEGMENT_BREAK_GAP_SECS: f64 = 1.0;
+/// Keep cues short enough to read
+const MAX_SEGMENT_WORDS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Json,
+}
+
+impl SubtitleFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "srt" => Ok(Self::Srt),
+            "vtt" | "webvtt" => Ok(Self::Vtt),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Unknown subtitle format: {}", s)),
+        }
+    }
+}
+
+/// Shift segments from a chunk of a split file to their position in the whole file
+pub fn offset(segments: &mut [Segment], seconds: f64) {
+    for segment in segments {
+        segment.start += seconds;
+        segment.end += seconds;
+    }
+}
+
+pub fn offset_words(words: &mut [Word], seconds: f64) {
+    for word in words {
+        word.start += seconds;
+        word.end += seconds;
+    }
+}
+
+/// Group words into subtitle segments, breaking after sentence-ending
+/// punctuation, at long pauses, and when a segment gets too long
+pub fn segments_from_words(words: &[Word]) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut current: Vec<&Word> = Vec::new();
+
+    let mut flush = |current: &mut Vec<&Word>| {
+        if let (Some(first), Some(last)) = (current.first(), current.last()) {
+            segments.push(Segment {
+                start: first.start,
+                end: last.end,
+                text: current
+                    .iter()
+                    .map(|w| w.text.trim())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            });
+        }
+        current.clear();
+    };
+
+    for word in words {
+        if let Some(previous) = current.last() {
+            if word.start - previous.end > SEGMENT_BREAK_GAP_SECS {
+                flush(&mut current);
+            }
+        }
+        current.push(word);
+        if word.text.trim_end().ends_with(['.', '!', '?']) || current.len() >= MAX_SEGMENT_WORDS {
+            flush(&mut current);
+        }
+    }
+    flush(&mut current);
+    segments
+}
+
+/// Drop empty segments and make sure every cue has a positive duration and
+/// doesn't start before the previous one. Blank lines end a cue in both SRT
+/// and WebVTT, so they are removed from the text.
+fn clean(segments: &[Segment]) -> Vec<Segment> {
+    let mut cleaned: Vec<Segment> = Vec::with_capacity(segments.len());
+    for segment in segments {
+        let text = segment
+            .text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.is_empty() {
+            continue;
+        }
+        let previous_start = cleaned.last().map(|s| s.start).unwrap_or(0.0);
+        let start = segment.start.max(previous_start).max(0.0);
+        cleaned.push(Segment {
+            start,
+            end: segment.end.max(start + 0.001),
+            text,
+        });
+    }
+    cleaned
+}
+
+/// "HH:MM:SS{sep}mmm"; SRT uses a comma, WebVTT a dot
+fn format_timestamp(seconds: f64, separator: char) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let ms = total_ms % 1000;
+    let total_secs = total_ms / 1000;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        total_secs / 3600,
+        (total_secs / 60) % 60,
+        total_secs % 60,
+        separator,
+        ms
+    )
+}
+
+pub fn to_srt(segments: &[Segment]) -> String {
+    clean(segments)
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                format_timestamp(s.start, ','),
+                format_timestamp(s.end, ','),
+                s.text
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn to_vtt(segments: &[Segment]) -> String {
+    let mut out = String::from("WEBVTT\n");
+    for s in clean(segments) {
+        out.push_str(&format!(
+            "\n{} --> {}\n{}\n",
+            format_timestamp(s.start, '.'),
+            format_timestamp(s.end, '.'),
+            s.text
+        ));
+    }
+    out
+}
+
+pub fn to_json(segments: &[Segment]) -> Result<String, String> {
+    serde_json::to_string_pretty(&clean(segments)).map_err(|e| e.to_string())
+}
+
+pub fn render(segments: &[Segment], format: SubtitleFormat) -> Result<String, String> {
+    match format {
+        SubtitleFormat::Srt => Ok(to_srt(segments)),
+        SubtitleFormat::Vtt => Ok(to_vtt(segments)),
+        SubtitleFormat::Json => to_json(segments),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, end: f64, text: &str) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    fn word(start: f64, end: f64, text: &str) -> Word {
+        Word {
+            start,
+            end,
+            text: text.to_string(),
+            probability: None,
+        }
+    }
+
+    fn timings(segments: &[Segment]) -> Vec<(f64, f64, &str)> {
+        segments
+            .iter()
+            .map(|s| (s.start, s.end, s.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn formats_timestamps_with_the_given_separator() {
+        assert_eq!(format_timestamp(0.0, ','), "00:00:00,000");
+        assert_eq!(format_timestamp(3723.4567, ','), "01:02:03,457");
+        assert_eq!(format_timestamp(59.9996, '.'), "00:01:00.000");
+        assert_eq!(format_timestamp(-1.0, '.'), "00:00:00.000");
+    }
+
+    #[test]
+    fn clean_drops_blank_lines_and_empty_segments() {
+        let cleaned = clean(&[
+            segment(0.0, 1.0, " first \n\n second "),
+            segment(1.0, 2.0, "  \n "),
+            segment(2.0, 3.0, "third"),
+        ]);
+        assert_eq!(
+            timings(&cleaned),
+            vec![(0.0, 1.0, "first\nsecond"), (2.0, 3.0, "third")]
+        );
+    }
+
+    #[test]
+    fn clean_keeps_cues_ordered_with_positive_durations() {
+        let cleaned = clean(&[
+            segment(-0.5, 1.0, "negative start"),
+            segment(2.0, 2.0, "zero length"),
+            segment(1.5, 3.0, "starts too early"),
+        ]);
+        assert_eq!(
+            timings(&cleaned),
+            vec![
+                (0.0, 1.0, "negative start"),
+                (2.0, 2.001, "zero length"),
+                (2.0, 3.0, "starts too early"),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_srt_and_vtt_cues() {
+        let segments = [segment(0.0, 1.5, "Hello."), segment(2.0, 3.25, "World.")];
+        assert_eq!(
+            to_srt(&segments),
+            "1\n00:00:00,000 --> 00:00:01,500\nHello.\n\n2\n00:00:02,000 --> 00:00:03,250\nWorld.\n"
+        );
+        assert_eq!(
+            to_vtt(&segments),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.500\nHello.\n\n00:00:02.000 --> 00:00:03.250\nWorld.\n"
+        );
+    }
+
+    #[test]
+    fn segments_break_at_sentence_ends_and_long_pauses() {
+        let words = [
+            word(0.0, 0.5, "Hello"),
+            word(0.5, 1.0, "there."),
+            word(1.2, 1.5, "How"),
+            word(3.0, 3.5, "are"),
+            word(3.5, 4.0, "you?"),
+        ];
+        assert_eq!(
+            timings(&segments_from_words(&words)),
+            vec![
+                (0.0, 1.0, "Hello there."),
+                (1.2, 1.5, "How"),
+                (3.0, 4.0, "are you?"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,26 @@
+// Synthetic audio shared by the VAD, decoding and chunking tests.
+
+use crate::vad::FRAME_MS;
+
+pub const RATE: u32 = 16000;
+
+/// Stand-in for speech: a 200Hz tone with a 20ms dip every 500ms, short
+/// enough for the hangover to bridge. The dips also let the noise floor
+/// settle when a window starts mid-speech.
+pub fn speech(secs: f64) -> Vec<f32> {
+    let period = RATE as usize / 2;
+    let dip = RATE as usize * FRAME_MS / 1000;
+    (0..(secs * RATE as f64) as usize)
+        .map(|i| {
+            if i % period >= period - dip {
+                0.0
+            } else {
+                0.3 * (2.0 * std::f32::consts::PI * 200.0 * i as f32 / RATE as f32).sin()
+            }
+        })
+        .collect()
+}
+
+pub fn silence(secs: f64) -> Vec<f32> {
+    vec![0.0; (secs * RATE as f64) as usize]
+}
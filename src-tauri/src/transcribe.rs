@@ -1,3 +1,5 @@
+use crate::decode;
+use crate::subtitles::{Segment, Word};
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -18,7 +20,7 @@ const AUDIO_SAMPLE_RATE: &str = "24000";
 const AUDIO_CHANNELS: &str = "1";
 /// Audio quality level for libmp3lame (0-9, lower is better)
 const AUDIO_QUALITY: &str = "2";
-/// Duration of each chunk when splitting large files (10 minutes)
+/// Longest chunk when splitting large files (10 minutes, ~19MB as 16kHz WAV)
 pub const CHUNK_DURATION_SECONDS: u32 = 600;
 
 /// Status of external dependencies (yt-dlp, ffmpeg)
@@ -37,6 +39,14 @@ pub struct TranscriptionResult {
     pub processed_text: Option<String>,
     pub duration_seconds: f64,
     pub word_count: usize,
+    /// Timed segments of the transcript, with local rules applied when
+    /// rules or a mode were requested; empty if the engine has no timestamps
+    #[serde(default)]
+    pub segments: Vec<Segment>,
+    /// Word timings of the transcript as recognized (local rules are not
+    /// applied), with confidence where the engine reports it
+    #[serde(default)]
+    pub words: Vec<Word>,
 }
 
 /// Supported audio formats that Groq API accepts directly
@@ -112,8 +122,16 @@ pub fn is_supported_format(path: &Path) -> bool {
     is_supported_audio(path) || is_supported_video(path)
 }
 
-/// Get audio duration in seconds using ffprobe
+/// Get audio duration in seconds, read from the container in-process with
+/// ffprobe as a fallback
 pub fn get_audio_duration(path: &Path) -> Result<f64, String> {
+    decode::probe_duration(path).or_else(|e| {
+        println!("[Transcribe] Native duration probe failed ({}), trying ffprobe", e);
+        get_audio_duration_ffprobe(path)
+    })
+}
+
+fn get_audio_duration_ffprobe(path: &Path) -> Result<f64, String> {
     let output = Command::new("ffprobe")
         .args([
             "-v", "error",
@@ -333,48 +351,3 @@ pub fn needs_chunking(path: &Path) -> Result<bool, String> {
     let size = get_file_size(path)?;
     Ok(size > MAX_DIRECT_UPLOAD_SIZE)
 }
-
-/// Split audio file into chunks for large file processing
-/// Returns paths to chunk files
-pub fn split_audio_file(
-    input_path: &Path,
-    output_dir: &Path,
-    chunk_duration_seconds: u32,
-) -> Result<Vec<PathBuf>, String> {
-    let output_pattern = output_dir.join("chunk_%03d.mp3");
-
-    let status = Command::new("ffmpeg")
-        .args([
-            "-i",
-            input_path.to_str().ok_or("Invalid input path")?,
-            "-f", "segment",
-            "-segment_time",
-            &chunk_duration_seconds.to_string(),
-            "-c", "copy",
-            "-y",
-        ])
-        .arg(output_pattern.to_str().ok_or("Invalid output pattern")?)
-        .output()
-        .map_err(|e| format!("Failed to run ffmpeg: {}", e))?;
-
-    if !status.status.success() {
-        let stderr = String::from_utf8_lossy(&status.stderr);
-        return Err(format!("ffmpeg split failed: {}", stderr));
-    }
-
-    // Collect all chunk files
-    let mut chunks: Vec<PathBuf> = std::fs::read_dir(output_dir)
-        .map_err(|e| format!("Failed to read output dir: {}", e))?
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| {
-            p.file_name()
-                .and_then(|n| n.to_str())
-                .map(|n| n.starts_with("chunk_"))
-                .unwrap_or(false)
-        })
-        .collect();
-
-    chunks.sort();
-    Ok(chunks)
-}
@@ -0,0 +1,261 @@
+// Type-out insertion: sends text as synthetic keystrokes instead of pasting,
+// for terminals and apps that block or mangle clipboard paste.
+//
+// macOS posts CGEvents carrying unicode strings (enigo's layout lookups need
+// the main thread there); other platforms use enigo's `Keyboard::text`.
+// Either way, modifiers still held from the shortcut must not combine with the
+// typed keys: macOS clears the flags on each event, other platforms release the
+// modifier keys before typing starts.
+
+use std::time::Duration;
+
+/// CGEventKeyboardSetUnicodeString accepts at most 20 UTF-16 units per event
+const TYPE_CHUNK_UTF16_UNITS: usize = 20;
+/// Pause between chunks so slow apps don't drop or reorder keystrokes
+const TYPE_CHUNK_DELAY_MS: u64 = 8;
+/// Longer pause after Return, which can trigger work in the target app
+const NEWLINE_DELAY_MS: u64 = 25;
+
+/// How dictated text gets into the focused app
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InsertionMethod {
+    /// Copy to the clipboard and simulate Cmd/Ctrl+V
+    Paste,
+    /// Type the text out as keystrokes, leaving the clipboard alone
+    Type,
+}
+
+impl InsertionMethod {
+    pub fn from_store_value(value: &str) -> Option<Self> {
+        match value {
+            "paste" => Some(Self::Paste),
+            "type" => Some(Self::Type),
+            _ => None,
+        }
+    }
+}
+
+/// Typing that stopped partway through the text
+#[derive(Debug)]
+pub struct TypingError {
+    pub message: String,
+    /// Byte length of the prefix of the text that was typed before the failure
+    pub typed_len: usize,
+}
+
+/// Split a line into pieces of at most `max_units` UTF-16 code units,
+/// never splitting a character
+fn utf16_chunks(line: &str, max_units: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units + c.len_utf16() > max_units {
+            chunks.push(&line[start..i]);
+            start = i;
+            units = 0;
+        }
+        units += c.len_utf16();
+    }
+    if start < line.len() {
+        chunks.push(&line[start..]);
+    }
+    chunks
+}
+
+/// Where keystrokes go; the platform `Typer`, or a recorder in tests
+trait Keystrokes {
+    fn type_chunk(&mut self, chunk: &str) -> Result<(), String>;
+    fn press_return(&mut self) -> Result<(), String>;
+}
+
+/// Type text into the focused app. Blocking; run it off the async runtime.
+/// Newlines are sent as Return key presses. On failure the error says how
+/// much of the text already reached the app.
+pub fn type_text(text: &str) -> Result<(), TypingError> {
+    let mut typer = Typer::new().map_err(|message| TypingError {
+        message,
+        typed_len: 0,
+    })?;
+    send_text(&mut typer, text)
+}
+
+fn send_text(typer: &mut impl Keystrokes, text: &str) -> Result<(), TypingError> {
+    let mut typed_len = 0;
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            typer
+                .press_return()
+                .map_err(|message| TypingError { message, typed_len })?;
+            typed_len += 1;
+            std::thread::sleep(Duration::from_millis(NEWLINE_DELAY_MS));
+        }
+        let content = line.trim_end_matches('\r');
+        for chunk in utf16_chunks(content, TYPE_CHUNK_UTF16_UNITS) {
+            typer
+                .type_chunk(chunk)
+                .map_err(|message| TypingError { message, typed_len })?;
+            typed_len += chunk.len();
+            std::thread::sleep(Duration::from_millis(TYPE_CHUNK_DELAY_MS));
+        }
+        // Carriage returns are covered by the Return press
+        typed_len += line.len() - content.len();
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+struct Typer {
+    source: core_graphics::event_source::CGEventSource,
+}
+
+#[cfg(target_os = "macos")]
+impl Typer {
+    fn new() -> Result<Self, String> {
+        use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+        let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState)
+            .map_err(|_| "Failed to create CGEventSource".to_string())?;
+        Ok(Self { source })
+    }
+
+    fn post_key(&self, keycode: u16, text: Option<&str>) -> Result<(), String> {
+        use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation};
+
+        let key_down = CGEvent::new_keyboard_event(self.source.clone(), keycode, true)
+            .map_err(|_| "Failed to create key-down event".to_string())?;
+        let key_up = CGEvent::new_keyboard_event(self.source.clone(), keycode, false)
+            .map_err(|_| "Failed to create key-up event".to_string())?;
+
+        // Modifiers still held from the shortcut must not turn text into commands
+        key_down.set_flags(CGEventFlags::CGEventFlagNull);
+        key_up.set_flags(CGEventFlags::CGEventFlagNull);
+        if let Some(text) = text {
+            key_down.set_string(text);
+        }
+
+        key_down.post(CGEventTapLocation::HID);
+        key_up.post(CGEventTapLocation::HID);
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Keystrokes for Typer {
+    fn type_chunk(&mut self, chunk: &str) -> Result<(), String> {
+        // The keycode is ignored by apps when a unicode string is attached
+        self.post_key(0, Some(chunk))
+    }
+
+    fn press_return(&mut self) -> Result<(), String> {
+        // kVK_Return
+        self.post_key(36, None)
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+struct Typer {
+    enigo: enigo::Enigo,
+}
+
+#[cfg(not(target_os = "macos"))]
+impl Typer {
+    fn new() -> Result<Self, String> {
+        use enigo::{Direction, Key, Keyboard};
+        let mut enigo = enigo::Enigo::new(&enigo::Settings::default())
+            .map_err(|e| format!("Failed to create Enigo: {:?}", e))?;
+
+        // The shortcut's modifiers may still be down when typing starts; typing
+        // "c" with Ctrl held would copy instead of inserting text
+        for modifier in [Key::Control, Key::Shift, Key::Alt, Key::Meta] {
+            enigo
+                .key(modifier, Direction::Release)
+                .map_err(|e| format!("Failed to release modifier keys: {:?}", e))?;
+        }
+        Ok(Self { enigo })
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+impl Keystrokes for Typer {
+    fn type_chunk(&mut self, chunk: &str) -> Result<(), String> {
+        use enigo::Keyboard;
+        self.enigo
+            .text(chunk)
+            .map_err(|e| format!("Failed to type text: {:?}", e))
+    }
+
+    fn press_return(&mut self) -> Result<(), String> {
+        use enigo::{Direction, Key, Keyboard};
+        self.enigo
+            .key(Key::Return, Direction::Click)
+            .map_err(|e| format!("Failed to press Return: {:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records keystrokes as text, failing once `budget` events were sent
+    struct Recorder {
+        typed: String,
+        budget: usize,
+    }
+
+    impl Recorder {
+        fn spend(&mut self) -> Result<(), String> {
+            if self.budget == 0 {
+                return Err("target app went away".to_string());
+            }
+            self.budget -= 1;
+            Ok(())
+        }
+    }
+
+    impl Keystrokes for Recorder {
+        fn type_chunk(&mut self, chunk: &str) -> Result<(), String> {
+            self.spend()?;
+            self.typed.push_str(chunk);
+            Ok(())
+        }
+
+        fn press_return(&mut self) -> Result<(), String> {
+            self.spend()?;
+            self.typed.push('\n');
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn chunks_stay_within_the_utf16_limit() {
+        assert_eq!(utf16_chunks("abcdefg", 3), vec!["abc", "def", "g"]);
+        assert_eq!(utf16_chunks("", 3), Vec::<&str>::new());
+        // "é" is one unit, "😀" two, so neither is split
+        assert_eq!(utf16_chunks("é😀a😀", 3), vec!["é😀", "a😀"]);
+        assert_eq!(utf16_chunks("😀😀", 3), vec!["😀", "😀"]);
+    }
+
+    #[test]
+    fn types_every_line_and_chunk() {
+        let mut recorder = Recorder {
+            typed: String::new(),
+            budget: usize::MAX,
+        };
+        send_text(&mut recorder, "first line\r\nsecond 😀 line").unwrap();
+        assert_eq!(recorder.typed, "first line\nsecond 😀 line");
+    }
+
+    #[test]
+    fn typed_len_covers_the_text_sent_before_a_failure() {
+        let text = "héllo wörld, this line is long enough\r\nnext";
+        for budget in 0..4 {
+            let mut recorder = Recorder {
+                typed: String::new(),
+                budget,
+            };
+            let error = send_text(&mut recorder, text).unwrap_err();
+            let sent = text[..error.typed_len].replace('\r', "");
+            assert_eq!(sent, recorder.typed, "budget {}", budget);
+        }
+    }
+}
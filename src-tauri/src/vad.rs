@@ -0,0 +1,387 @@
+// Voice activity detection for captured dictation audio.
+//
+// Frame-based detector combining short-term energy against an adaptive noise
+// floor with zero-crossing rate as a cheap spectral cue (broadband hiss and
+// fan noise cross zero far more often than voiced speech). A hangover keeps
+// short pauses between words inside one speech run.
+
+use serde::Serialize;
+
+pub(crate) const FRAME_MS: usize = 20;
+
+// PCM16 conversion constant
+const PCM16_NORMALIZE: f32 = 32768.0;
+
+/// Frames whose zero-crossing rate exceeds this are treated as noise unless
+/// they are also well above the energy threshold (e.g. loud fricatives)
+const MAX_SPEECH_ZCR: f32 = 0.35;
+
+/// Energy multiple of the speech threshold that counts as speech regardless of ZCR
+const STRONG_SPEECH_MULTIPLIER: f32 = 2.0;
+
+/// Noise floor smoothing for non-speech frames (closer to 1.0 = slower)
+const NOISE_FLOOR_DECAY: f32 = 0.95;
+
+/// Much slower smoothing applied during speech, so a floor seeded below the
+/// room's noise still climbs to it (in ~4s) instead of treating the noise as
+/// speech forever. Pauses between words pull it back down right away.
+const SPEECH_NOISE_FLOOR_DECAY: f32 = 0.998;
+
+/// Percentile of frame energies taken as the noise floor when the whole
+/// signal is known up front
+const NOISE_FLOOR_PERCENTILE: f32 = 0.1;
+
+/// Shortest gap between speech runs that counts as a pause to split at. The
+/// hangover already bridges shorter gaps, so real pauses are longer than this.
+const MIN_PAUSE_MS: usize = 200;
+
+#[derive(Debug, Clone)]
+pub struct VadConfig {
+    /// Speech must exceed the noise floor by this factor (3.0 ≈ +9.5 dB)
+    pub energy_ratio: f32,
+    /// Absolute RMS below which a frame is never speech (≈ -50 dBFS)
+    pub min_rms: f32,
+    /// How long speech state is held after energy drops
+    pub hangover_ms: usize,
+    /// Speech runs shorter than this are discarded as clicks or bumps
+    pub min_speech_ms: usize,
+    /// Silence kept around trimmed speech so word onsets and tails survive
+    pub padding_ms: usize,
+}
+
+impl Default for VadConfig {
+    fn default() -> Self {
+        Self {
+            energy_ratio: 3.0,
+            min_rms: 0.003,
+            hangover_ms: 300,
+            min_speech_ms: 120,
+            padding_ms: 300,
+        }
+    }
+}
+
+/// A contiguous run of detected speech, in samples of the analysed signal
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct SpeechSegment {
+    pub start_sample: usize,
+    pub end_sample: usize,
+}
+
+impl SpeechSegment {
+    pub fn start_secs(&self, sample_rate: u32) -> f64 {
+        self.start_sample as f64 / sample_rate as f64
+    }
+
+    pub fn end_secs(&self, sample_rate: u32) -> f64 {
+        self.end_sample as f64 / sample_rate as f64
+    }
+}
+
+/// Streaming detector. Feed it samples in any chunk size; it classifies each
+/// complete frame and carries partial frames over to the next call.
+pub struct VoiceActivityDetector {
+    config: VadConfig,
+    frame_len: usize,
+    hangover_frames: usize,
+    pending: Vec<f32>,
+    noise_floor: f32,
+    hangover_left: usize,
+}
+
+impl VoiceActivityDetector {
+    /// Streaming detector. The noise floor starts low enough that the first
+    /// frames are judged against `min_rms` alone, so speech from the very
+    /// first frame is kept rather than mistaken for the room's noise.
+    pub fn new(sample_rate: u32, config: VadConfig) -> Self {
+        let noise_floor = config.min_rms / config.energy_ratio;
+        Self::with_noise_floor(sample_rate, config, noise_floor)
+    }
+
+    /// Detector starting from a known noise floor (RMS)
+    pub fn with_noise_floor(sample_rate: u32, config: VadConfig, noise_floor: f32) -> Self {
+        let frame_len = samples_per_frame(sample_rate);
+        let hangover_frames = config.hangover_ms / FRAME_MS;
+        Self {
+            config,
+            frame_len,
+            hangover_frames,
+            pending: Vec::with_capacity(frame_len),
+            noise_floor,
+            hangover_left: 0,
+        }
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame_len
+    }
+
+    /// Classify every complete frame in `samples`; returns one flag per frame
+    pub fn process(&mut self, samples: &[f32]) -> Vec<bool> {
+        let mut decisions =
+            Vec::with_capacity((self.pending.len() + samples.len()) / self.frame_len);
+        let mut input = std::mem::take(&mut self.pending);
+        input.extend_from_slice(samples);
+
+        let mut frames = input.chunks_exact(self.frame_len);
+        for frame in &mut frames {
+            decisions.push(self.classify_frame(frame));
+        }
+        self.pending = frames.remainder().to_vec();
+
+        decisions
+    }
+
+    fn classify_frame(&mut self, frame: &[f32]) -> bool {
+        let rms = frame_rms(frame);
+        let crossings = frame
+            .windows(2)
+            .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+            .count();
+        let zcr = crossings as f32 / frame.len() as f32;
+
+        let floor = self.noise_floor;
+        let threshold = (floor * self.config.energy_ratio).max(self.config.min_rms);
+        let is_voiced =
+            rms > threshold && (zcr < MAX_SPEECH_ZCR || rms > threshold * STRONG_SPEECH_MULTIPLIER);
+
+        // Track the floor quickly downwards and slowly upwards so a burst of
+        // speech never drags it up to speech level.
+        let decay = if is_voiced {
+            SPEECH_NOISE_FLOOR_DECAY
+        } else {
+            NOISE_FLOOR_DECAY
+        };
+        self.noise_floor = if rms < floor {
+            rms
+        } else {
+            floor * decay + rms * (1.0 - decay)
+        };
+
+        if is_voiced {
+            self.hangover_left = self.hangover_frames;
+            return true;
+        }
+
+        if self.hangover_left > 0 {
+            self.hangover_left -= 1;
+            return true;
+        }
+        false
+    }
+}
+
+fn samples_per_frame(sample_rate: u32) -> usize {
+    (sample_rate as usize * FRAME_MS / 1000).max(1)
+}
+
+fn frame_rms(frame: &[f32]) -> f32 {
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+/// Noise floor of a whole signal: a low percentile of its frame energies, so
+/// neither speech at the start nor loud passages raise it
+fn estimate_noise_floor(samples: &[f32], frame_len: usize) -> f32 {
+    let mut energies: Vec<f32> = samples.chunks_exact(frame_len).map(frame_rms).collect();
+    if energies.is_empty() {
+        return 0.0;
+    }
+    energies.sort_by(f32::total_cmp);
+    energies[((energies.len() - 1) as f32 * NOISE_FLOOR_PERCENTILE) as usize]
+}
+
+/// Find speech segments in a mono signal
+pub fn detect_speech_segments(
+    samples: &[f32],
+    sample_rate: u32,
+    config: &VadConfig,
+) -> Vec<SpeechSegment> {
+    let frame_len = samples_per_frame(sample_rate);
+    let noise_floor = estimate_noise_floor(samples, frame_len);
+    let mut detector =
+        VoiceActivityDetector::with_noise_floor(sample_rate, config.clone(), noise_floor);
+    let min_speech_samples = sample_rate as usize * config.min_speech_ms / 1000;
+
+    let mut segments = Vec::new();
+    let mut run_start: Option<usize> = None;
+
+    for (i, is_speech) in detector.process(samples).into_iter().enumerate() {
+        let frame_start = i * frame_len;
+        match (is_speech, run_start) {
+            (true, None) => run_start = Some(frame_start),
+            (false, Some(start)) => {
+                if frame_start - start >= min_speech_samples {
+                    segments.push(SpeechSegment {
+                        start_sample: start,
+                        end_sample: frame_start,
+                    });
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(start) = run_start {
+        if samples.len() - start >= min_speech_samples {
+            segments.push(SpeechSegment {
+                start_sample: start,
+                end_sample: samples.len(),
+            });
+        }
+    }
+
+    segments
+}
+
+/// Sample index in the middle of the longest pause at or after `search_from`,
+/// for splitting long audio without cutting words. The whole signal is
+/// analysed so the noise floor is settled by the time the search starts.
+/// Returns `None` if speech never pauses long enough in that range.
+pub fn find_pause(
+    samples: &[f32],
+    sample_rate: u32,
+    search_from: usize,
+    config: &VadConfig,
+) -> Option<usize> {
+    let segments = detect_speech_segments(samples, sample_rate, config);
+    let min_pause = sample_rate as usize * MIN_PAUSE_MS / 1000;
+
+    // Gaps between speech runs, including leading and trailing silence
+    let mut gaps = Vec::with_capacity(segments.len() + 1);
+    let mut previous_end = 0;
+    for segment in &segments {
+        gaps.push((previous_end, segment.start_sample));
+        previous_end = segment.end_sample;
+    }
+    gaps.push((previous_end, samples.len()));
+
+    gaps.into_iter()
+        .map(|(start, end)| (start.max(search_from), end))
+        .filter(|(start, end)| end > start && end - start >= min_pause)
+        .max_by_key(|(start, end)| end - start)
+        .map(|(start, end)| start + (end - start) / 2)
+}
+
+/// Result of trimming a PCM16 recording down to its speech
+pub struct TrimmedAudio {
+    /// PCM16 LE covering the first to last speech segment plus padding;
+    /// empty when no speech was detected
+    pub pcm16: Vec<u8>,
+    /// Speech segments relative to the original (untrimmed) audio
+    pub segments: Vec<SpeechSegment>,
+}
+
+/// Drop leading and trailing silence from PCM16 LE mono audio. Pauses between
+/// speech segments are kept so the engine still sees natural phrasing.
+pub fn trim_silence_pcm16(pcm16: &[u8], sample_rate: u32, config: &VadConfig) -> TrimmedAudio {
+    let samples: Vec<f32> = pcm16
+        .chunks_exact(2)
+        .map(|c| i16::from_le_bytes([c[0], c[1]]) as f32 / PCM16_NORMALIZE)
+        .collect();
+
+    let segments = detect_speech_segments(&samples, sample_rate, config);
+    let (first, last) = match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            return TrimmedAudio {
+                pcm16: Vec::new(),
+                segments,
+            }
+        }
+    };
+
+    let padding = sample_rate as usize * config.padding_ms / 1000;
+    let start = first.start_sample.saturating_sub(padding);
+    let end = (last.end_sample + padding).min(samples.len());
+
+    TrimmedAudio {
+        pcm16: pcm16[start * 2..end * 2].to_vec(),
+        segments,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_audio::{silence, speech, RATE};
+    use crate::wav;
+
+    fn secs(seconds: f64) -> usize {
+        (seconds * RATE as f64) as usize
+    }
+
+    fn trim(samples: &[f32]) -> TrimmedAudio {
+        trim_silence_pcm16(&wav::f32_to_pcm16(samples), RATE, &VadConfig::default())
+    }
+
+    #[test]
+    fn trim_drops_leading_and_trailing_silence_around_padding() {
+        let samples = [silence(2.0), speech(2.0), silence(2.0)].concat();
+        let trimmed = trim(&samples);
+
+        assert_eq!(trimmed.segments.len(), 1);
+        let segment = trimmed.segments[0];
+        assert_eq!(segment.start_sample, secs(2.0));
+        // The hangover holds speech a little past its end
+        assert!(segment.end_sample >= secs(4.0) && segment.end_sample <= secs(4.3));
+
+        let padding = secs(0.3);
+        let kept = (segment.start_sample - padding)..(segment.end_sample + padding);
+        assert_eq!(trimmed.pcm16, wav::f32_to_pcm16(&samples[kept]));
+    }
+
+    #[test]
+    fn trim_of_silence_is_empty() {
+        let trimmed = trim(&silence(3.0));
+        assert!(trimmed.segments.is_empty());
+        assert!(trimmed.pcm16.is_empty());
+    }
+
+    #[test]
+    fn trim_keeps_speech_starting_at_the_first_frame() {
+        let samples = [speech(2.0), silence(2.0)].concat();
+        let trimmed = trim(&samples);
+
+        assert_eq!(trimmed.segments.len(), 1);
+        assert_eq!(trimmed.segments[0].start_sample, 0);
+        let pcm16 = wav::f32_to_pcm16(&samples);
+        assert!(pcm16.starts_with(&trimmed.pcm16));
+        assert!(trimmed.pcm16.len() < pcm16.len());
+    }
+
+    #[test]
+    fn find_pause_picks_the_longest_gap_after_search_start() {
+        // 0.7s pause after the hangover at 3s, a 0.1s one at 6s that the
+        // hangover bridges, and leading silence before the search start
+        let samples = [
+            silence(1.0),
+            speech(2.0),
+            silence(1.0),
+            speech(2.0),
+            silence(0.1),
+            speech(1.9),
+        ]
+        .concat();
+        let cut = find_pause(&samples, RATE, secs(2.0), &VadConfig::default()).unwrap();
+        assert!(cut > secs(3.0) && cut < secs(4.0), "cut at {}", cut);
+    }
+
+    #[test]
+    fn find_pause_ignores_gaps_clipped_too_short() {
+        let samples = [silence(1.0), speech(2.0), silence(1.0), speech(2.0)].concat();
+        assert_eq!(
+            find_pause(&samples, RATE, secs(3.9), &VadConfig::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn find_pause_finds_nothing_in_continuous_speech() {
+        let samples = speech(8.0);
+        assert_eq!(
+            find_pause(&samples, RATE, secs(1.0), &VadConfig::default()),
+            None
+        );
+    }
+}
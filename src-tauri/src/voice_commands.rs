@@ -0,0 +1,429 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+/// What a spoken command does to the transcript
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum CommandAction {
+    /// Attach to the previous word, e.g. "," or "?"
+    Punctuation(String),
+    /// Insert as a separate word, e.g. "@" or ":)"
+    Text(String),
+    NewLine,
+    NewParagraph,
+    /// Remove the sentence dictated before the command
+    DeleteLastSentence,
+    /// Keep the command phrase that follows as plain words, e.g. "literal
+    /// period" for the word "period"
+    Literal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoiceCommand {
+    pub phrase: String,
+    pub action: CommandAction,
+}
+
+fn punctuation(phrase: &str, mark: &str) -> VoiceCommand {
+    VoiceCommand {
+        phrase: phrase.to_string(),
+        action: CommandAction::Punctuation(mark.to_string()),
+    }
+}
+
+fn action(phrase: &str, action: CommandAction) -> VoiceCommand {
+    VoiceCommand {
+        phrase: phrase.to_string(),
+        action,
+    }
+}
+
+/// Built-in phrases for a language code from the `language` setting.
+/// Auto-detect and languages without a table fall back to English.
+pub fn builtin_commands(language: &str) -> Vec<VoiceCommand> {
+    use CommandAction::*;
+    match language {
+        "pl" => vec![
+            punctuation("przecinek", ","),
+            punctuation("kropka", "."),
+            punctuation("znak zapytania", "?"),
+            punctuation("wykrzyknik", "!"),
+            punctuation("dwukropek", ":"),
+            punctuation("średnik", ";"),
+            action("nowa linia", NewLine),
+            action("nowy akapit", NewParagraph),
+            action("skreśl to", DeleteLastSentence),
+            action("dosłownie", Literal),
+        ],
+        "de" => vec![
+            punctuation("Komma", ","),
+            punctuation("Punkt", "."),
+            punctuation("Fragezeichen", "?"),
+            punctuation("Ausrufezeichen", "!"),
+            punctuation("Doppelpunkt", ":"),
+            punctuation("Semikolon", ";"),
+            action("neue Zeile", NewLine),
+            action("neuer Absatz", NewParagraph),
+            action("streich das", DeleteLastSentence),
+            action("wörtlich", Literal),
+        ],
+        "es" => vec![
+            punctuation("coma", ","),
+            punctuation("punto", "."),
+            punctuation("signo de interrogación", "?"),
+            punctuation("signo de exclamación", "!"),
+            punctuation("dos puntos", ":"),
+            punctuation("punto y coma", ";"),
+            action("nueva línea", NewLine),
+            action("nuevo párrafo", NewParagraph),
+            action("borra eso", DeleteLastSentence),
+            action("literal", Literal),
+        ],
+        "fr" => vec![
+            punctuation("virgule", ","),
+            punctuation("point", "."),
+            punctuation("point d'interrogation", "?"),
+            punctuation("point d'exclamation", "!"),
+            punctuation("deux points", ":"),
+            punctuation("point-virgule", ";"),
+            action("nouvelle ligne", NewLine),
+            action("nouveau paragraphe", NewParagraph),
+            action("efface ça", DeleteLastSentence),
+            action("littéralement", Literal),
+        ],
+        _ => vec![
+            punctuation("comma", ","),
+            punctuation("period", "."),
+            punctuation("full stop", "."),
+            punctuation("question mark", "?"),
+            punctuation("exclamation mark", "!"),
+            punctuation("exclamation point", "!"),
+            punctuation("colon", ":"),
+            punctuation("semicolon", ";"),
+            action("new line", NewLine),
+            action("new paragraph", NewParagraph),
+            action("scratch that", DeleteLastSentence),
+            action("literal", Literal),
+        ],
+    }
+}
+
+/// Punctuation the STT engine adds around a spoken command ("Hello, comma.")
+fn is_stt_punctuation(c: char) -> bool {
+    matches!(c, ',' | '.' | ';' | ':' | '!' | '?')
+}
+
+fn is_sentence_end(c: char) -> bool {
+    matches!(c, '.' | '!' | '?')
+}
+
+fn is_single_word(phrase: &str) -> bool {
+    phrase
+        .split(|ch: char| ch.is_whitespace() || ch == '-')
+        .filter(|w| !w.is_empty())
+        .count()
+        == 1
+}
+
+/// Whether a match stands apart from the surrounding prose: after a pause the
+/// engine marked with punctuation or a line break, or alone at the start of
+/// the transcript ("Period." or "Comma, ...")
+fn after_pause(transcript: &str, start: usize, end: usize) -> bool {
+    let before = transcript[..start].trim_end_matches([' ', '\t']);
+    let after = transcript[end..].trim_start_matches([' ', '\t']);
+    let pause = |c: char| is_stt_punctuation(c) || c == '\n' || c == '\r';
+    if before.is_empty() {
+        after.is_empty() || after.starts_with(pause)
+    } else {
+        before.ends_with(pause)
+    }
+}
+
+/// Phrase pattern; engines write "new line", "newline" and "new-line"
+/// interchangeably
+fn phrase_pattern(phrase: &str) -> String {
+    let words: Vec<String> = phrase
+        .split(|ch: char| ch.is_whitespace() || ch == '-')
+        .filter(|w| !w.is_empty())
+        .map(regex::escape)
+        .collect();
+    words.join(r"[\s-]*")
+}
+
+/// Custom commands come first so they override built-ins with the same phrase.
+/// Longer phrases are tried first so "punto y coma" wins over "punto".
+/// Literal phrases become an optional `literal` prefix group; each other
+/// command gets one capture group, in order, after it.
+fn build_matcher(commands: &[VoiceCommand]) -> Option<(Regex, Vec<&VoiceCommand>)> {
+    let mut ordered: Vec<&VoiceCommand> = Vec::with_capacity(commands.len());
+    for command in commands {
+        let phrase = command.phrase.trim();
+        if phrase.is_empty()
+            || ordered
+                .iter()
+                .any(|c| c.phrase.trim().to_lowercase() == phrase.to_lowercase())
+        {
+            continue;
+        }
+        ordered.push(command);
+    }
+    let (literals, mut ordered): (Vec<&VoiceCommand>, Vec<&VoiceCommand>) = ordered
+        .into_iter()
+        .partition(|c| c.action == CommandAction::Literal);
+    if ordered.is_empty() {
+        return None;
+    }
+    ordered.sort_by_key(|c| std::cmp::Reverse(c.phrase.trim().len()));
+
+    let alternation = ordered
+        .iter()
+        .map(|c| format!("({})", phrase_pattern(&c.phrase)))
+        .collect::<Vec<_>>()
+        .join("|");
+    let literal = if literals.is_empty() {
+        String::new()
+    } else {
+        let escapes = literals
+            .iter()
+            .map(|c| phrase_pattern(&c.phrase))
+            .collect::<Vec<_>>()
+            .join("|");
+        format!(r"(?:(?P<literal>{})[\s,:]+)?", escapes)
+    };
+
+    match RegexBuilder::new(&format!(r"\b{}(?:{})\b", literal, alternation))
+        .case_insensitive(true)
+        .build()
+    {
+        Ok(regex) => Some((regex, ordered)),
+        Err(e) => {
+            eprintln!("[VoiceCommands] Failed to build matcher: {}", e);
+            None
+        }
+    }
+}
+
+struct Output {
+    text: String,
+    capitalize_next: bool,
+}
+
+impl Output {
+    fn trim_trailing(&mut self, strip: impl Fn(char) -> bool) {
+        let len = self
+            .text
+            .trim_end_matches(|c: char| c == ' ' || c == '\t' || strip(c))
+            .len();
+        self.text.truncate(len);
+    }
+
+    /// Append dictated text, fixing up spacing and capitalization around commands
+    fn push_text(&mut self, segment: &str) {
+        let mut segment = segment;
+        if self.text.is_empty() || self.text.ends_with('\n') {
+            segment = segment.trim_start();
+        }
+        if segment.trim().is_empty() {
+            return;
+        }
+        if !self.text.is_empty()
+            && !self.text.ends_with(char::is_whitespace)
+            && !segment.starts_with(char::is_whitespace)
+            && !segment.starts_with(is_stt_punctuation)
+        {
+            self.text.push(' ');
+        }
+        if self.capitalize_next {
+            if let Some((i, c)) = segment.char_indices().find(|(_, c)| c.is_alphabetic()) {
+                self.text.push_str(&segment[..i]);
+                self.text.extend(c.to_uppercase());
+                segment = &segment[i + c.len_utf8()..];
+                self.capitalize_next = false;
+            }
+        }
+        self.text.push_str(segment);
+    }
+
+    fn apply(&mut self, action: &CommandAction) {
+        match action {
+            CommandAction::Punctuation(mark) => {
+                self.trim_trailing(is_stt_punctuation);
+                self.text.push_str(mark);
+                self.capitalize_next = mark.ends_with(is_sentence_end);
+            }
+            CommandAction::Text(text) => self.push_text(text),
+            CommandAction::NewLine | CommandAction::NewParagraph => {
+                self.trim_trailing(|c| c == ',');
+                let breaks = if *action == CommandAction::NewLine {
+                    "\n"
+                } else {
+                    "\n\n"
+                };
+                self.text.push_str(breaks);
+                self.capitalize_next = true;
+            }
+            // Consumed by the matcher together with the phrase it escapes
+            CommandAction::Literal => {}
+            CommandAction::DeleteLastSentence => {
+                self.trim_trailing(|c| matches!(c, ',' | ';' | ':'));
+                let body = self.text.trim_end_matches(is_sentence_end);
+                let cut = body
+                    .rfind(|c: char| is_sentence_end(c) || c == '\n')
+                    .map(|i| i + 1)
+                    .unwrap_or(0);
+                self.text.truncate(cut);
+                self.trim_trailing(|_| false);
+                self.capitalize_next = true;
+            }
+        }
+    }
+}
+
+/// Interpret spoken commands in a transcript. Punctuation the STT engine put
+/// around a command is dropped, since the command itself decides what goes there.
+/// One-word phrases ("period", "Punkt") are ordinary words too, so they only
+/// count as commands after a pause; elsewhere they are left as dictated.
+/// A literal phrase right before a command keeps the command as plain words,
+/// for prose that uses them ("the grace literal period is over").
+pub fn apply(transcript: &str, commands: &[VoiceCommand]) -> String {
+    let Some((regex, ordered)) = build_matcher(commands) else {
+        return transcript.to_string();
+    };
+
+    let mut output = Output {
+        text: String::with_capacity(transcript.len()),
+        capitalize_next: false,
+    };
+    let mut last = 0;
+    let first_group = regex.captures_len() - ordered.len();
+    for caps in regex.captures_iter(transcript) {
+        let Some(whole) = caps.get(0) else { continue };
+        if whole.start() < last {
+            continue;
+        }
+        let Some(index) = (first_group..caps.len()).find(|&i| caps.get(i).is_some()) else {
+            continue;
+        };
+        let command = ordered[index - first_group];
+        let literal = caps.name("literal").is_some();
+        if !literal
+            && is_single_word(&command.phrase)
+            && !after_pause(transcript, whole.start(), whole.end())
+        {
+            continue;
+        }
+        output.push_text(&transcript[last..whole.start()]);
+        if literal {
+            output.push_text(&caps[index]);
+            last = whole.end();
+            continue;
+        }
+        output.apply(&command.action);
+
+        let rest = &transcript[whole.end()..];
+        last = whole.end() + (rest.len() - rest.trim_start_matches(is_stt_punctuation).len());
+    }
+    output.push_text(&transcript[last..]);
+    output.trim_trailing(|_| false);
+    output.text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn english(transcript: &str) -> String {
+        apply(transcript, &builtin_commands("en"))
+    }
+
+    #[test]
+    fn replaces_commands_the_engine_punctuated() {
+        assert_eq!(
+            english("Dear John, comma, thanks for the update. Period."),
+            "Dear John, thanks for the update."
+        );
+    }
+
+    #[test]
+    fn replaces_multi_word_commands_in_unpunctuated_transcripts() {
+        assert_eq!(
+            english("dear john new paragraph thanks for the update question mark"),
+            "dear john\n\nThanks for the update?"
+        );
+    }
+
+    #[test]
+    fn keeps_single_word_phrases_in_prose() {
+        assert_eq!(english("the trial period ended"), "the trial period ended");
+        assert_eq!(
+            english("The trial period ended. Period."),
+            "The trial period ended."
+        );
+        assert_eq!(english("Period ended early."), "Period ended early.");
+        assert_eq!(
+            apply("das ist der Punkt", &builtin_commands("de")),
+            "das ist der Punkt"
+        );
+        assert_eq!(
+            apply("Das ist der Punkt.", &builtin_commands("de")),
+            "Das ist der Punkt."
+        );
+        assert_eq!(
+            apply(
+                "Es un punto importante, coma, sin duda.",
+                &builtin_commands("es")
+            ),
+            "Es un punto importante, sin duda."
+        );
+    }
+
+    #[test]
+    fn replaces_a_single_word_command_dictated_on_its_own() {
+        assert_eq!(english("Period."), ".");
+        assert_eq!(english("Comma, and then"), ", and then");
+    }
+
+    #[test]
+    fn matches_multi_word_phrases_in_any_spelling() {
+        assert_eq!(
+            english("first item new line second item newline third item question mark"),
+            "first item\nSecond item\nThird item?"
+        );
+        assert_eq!(
+            apply("uno punto y coma dos", &builtin_commands("es")),
+            "uno; dos"
+        );
+    }
+
+    #[test]
+    fn ignores_phrases_inside_longer_words() {
+        assert_eq!(
+            english("the commas are periodic"),
+            "the commas are periodic"
+        );
+    }
+
+    #[test]
+    fn literal_keeps_the_following_phrase_as_words() {
+        assert_eq!(
+            english("The grace literal period is over. Period."),
+            "The grace period is over."
+        );
+        assert_eq!(english("a literal translation"), "a literal translation");
+    }
+
+    #[test]
+    fn scratch_that_removes_the_previous_sentence() {
+        assert_eq!(
+            english("Keep this. Drop this scratch that and this stays"),
+            "Keep this. And this stays"
+        );
+    }
+
+    #[test]
+    fn custom_commands_override_builtins() {
+        let mut commands = vec![punctuation("period", "!")];
+        commands.extend(builtin_commands("en"));
+        assert_eq!(apply("Done. Period.", &commands), "Done!");
+    }
+}
@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+/// Size of the canonical PCM WAV header written by this module
+pub const WAV_HEADER_LEN: usize = 44;
+
+pub fn create_wav_header(
+    data_len: u32,
+    sample_rate: u32,
+    channels: u16,
+    bits_per_sample: u16,
+) -> Vec<u8> {
+    let byte_rate = sample_rate * channels as u32 * bits_per_sample as u32 / 8;
+    let block_align = channels * bits_per_sample / 8;
+    let chunk_size = 36 + data_len;
+
+    let mut header = Vec::with_capacity(WAV_HEADER_LEN);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&chunk_size.to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes()); // subchunk1 size
+    header.extend_from_slice(&1u16.to_le_bytes()); // audio format (PCM)
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&bits_per_sample.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+/// Float samples in [-1.0, 1.0] as PCM16 LE bytes
+pub fn f32_to_pcm16(samples: &[f32]) -> Vec<u8> {
+    samples
+        .iter()
+        .flat_map(|s| ((s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes())
+        .collect()
+}
+
+/// Streams mono PCM16 LE audio into a WAV file. The header is written with
+/// zero sizes up front and patched in [`WavWriter::finish`].
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    data_len: u64,
+}
+
+impl WavWriter {
+    pub fn create(file: File, sample_rate: u32) -> Result<Self, String> {
+        let mut writer = BufWriter::new(file);
+        writer
+            .write_all(&create_wav_header(0, sample_rate, 1, 16))
+            .map_err(|e| format!("Failed to write WAV header: {}", e))?;
+        Ok(Self {
+            writer,
+            data_len: 0,
+        })
+    }
+
+    pub fn write_pcm16(&mut self, pcm16: &[u8]) -> Result<(), String> {
+        // RIFF sizes are 32-bit; at 24kHz mono that is ~24 hours of audio
+        if self.data_len + pcm16.len() as u64 > (u32::MAX as u64 - WAV_HEADER_LEN as u64) {
+            return Err("Recording exceeds the maximum WAV file size".to_string());
+        }
+        self.writer
+            .write_all(pcm16)
+            .map_err(|e| format!("Failed to write audio to disk: {}", e))?;
+        self.data_len += pcm16.len() as u64;
+        Ok(())
+    }
+
+    /// Flush buffered audio and patch the RIFF and data chunk sizes
+    pub fn finish(mut self) -> Result<u64, String> {
+        let data_len = self.data_len as u32;
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush WAV file: {}", e))?;
+        let file = self.writer.get_mut();
+        file.seek(SeekFrom::Start(4))
+            .and_then(|_| file.write_all(&(36 + data_len).to_le_bytes()))
+            .and_then(|_| file.seek(SeekFrom::Start(40)))
+            .and_then(|_| file.write_all(&data_len.to_le_bytes()))
+            .and_then(|_| file.flush())
+            .map_err(|e| format!("Failed to finalize WAV header: {}", e))?;
+        Ok(self.data_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn header_describes_the_pcm_format() {
+        let header = create_wav_header(32000, 16000, 1, 16);
+        assert_eq!(header.len(), WAV_HEADER_LEN);
+        assert_eq!(&header[0..4], b"RIFF");
+        assert_eq!(u32_at(&header, 4), 36 + 32000);
+        assert_eq!(&header[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(&header, 24), 16000);
+        // Byte rate and block align for 16-bit mono
+        assert_eq!(u32_at(&header, 28), 32000);
+        assert_eq!(u16::from_le_bytes([header[32], header[33]]), 2);
+        assert_eq!(&header[36..40], b"data");
+        assert_eq!(u32_at(&header, 40), 32000);
+    }
+
+    #[test]
+    fn pcm16_conversion_clamps_out_of_range_samples() {
+        let pcm16 = f32_to_pcm16(&[0.0, 1.0, -1.0, 2.0, -2.0, 0.5]);
+        let samples: Vec<i16> = pcm16
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        assert_eq!(
+            samples,
+            vec![0, i16::MAX, -i16::MAX, i16::MAX, -i16::MAX, i16::MAX / 2]
+        );
+    }
+
+    #[test]
+    fn writer_patches_sizes_on_finish() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.wav");
+        let mut writer = WavWriter::create(File::create(&path).unwrap(), 24000).unwrap();
+        writer.write_pcm16(&[1, 0, 2, 0]).unwrap();
+        writer.write_pcm16(&[3, 0]).unwrap();
+        assert_eq!(writer.finish().unwrap(), 6);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), WAV_HEADER_LEN + 6);
+        assert_eq!(&bytes[..WAV_HEADER_LEN], create_wav_header(6, 24000, 1, 16));
+        assert_eq!(&bytes[WAV_HEADER_LEN..], [1, 0, 2, 0, 3, 0]);
+    }
+}
@@ -1,4 +1,7 @@
-use rubato::{FftFixedIn, Resampler};
+use crate::chunking::{self, WindowProgress};
+use crate::decode;
+use crate::stt::TimedTranscript;
+use crate::subtitles::{Segment, Word};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, MutexGuard};
 use tauri::{AppHandle, Emitter, Manager};
@@ -8,6 +11,8 @@ use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextPar
 const PCM16_NORMALIZE: f32 = 32768.0;
 const INPUT_SAMPLE_RATE: u32 = 24000;
 const WHISPER_SAMPLE_RATE: u32 = 16000;
+/// Segment timestamps are reported in 10ms units
+const TIMESTAMP_UNITS_PER_SEC: f64 = 100.0;
 
 /// Minimum interval between download progress events
 const PROGRESS_THROTTLE_MS: u128 = 100;
@@ -185,6 +190,22 @@ pub fn load_model(state: &WhisperState, model_dir: &Path) -> Result<(), String>
     Ok(())
 }
 
+/// Load the downloaded model unless it is loaded already, emitting the
+/// loading events around it. Blocks for as long as loading takes.
+pub fn ensure_model_loaded(app: &AppHandle, state: &WhisperState) -> Result<(), String> {
+    if is_model_loaded(state) {
+        return Ok(());
+    }
+    let model_dir = get_model_dir(app)?;
+    if !is_model_downloaded(&model_dir) {
+        return Err("Whisper model not downloaded. Download it in Settings.".to_string());
+    }
+    app.emit(EVENT_LOADING, true).ok();
+    let result = load_model(state, &model_dir);
+    app.emit(EVENT_LOADING, false).ok();
+    result
+}
+
 pub fn unload_model(state: &WhisperState) -> Result<(), String> {
     let mut model_guard = state.lock_model();
     *model_guard = None;
@@ -196,7 +217,12 @@ pub fn is_model_loaded(state: &WhisperState) -> bool {
     state.model.lock().map(|g| g.is_some()).unwrap_or(false)
 }
 
-pub fn transcribe_pcm16(state: &WhisperState, pcm16_24khz: Vec<u8>, language: &str) -> Result<String, String> {
+pub fn transcribe_pcm16(
+    state: &WhisperState,
+    pcm16_24khz: Vec<u8>,
+    language: &str,
+    initial_prompt: Option<&str>,
+) -> Result<String, String> {
     let mut model_guard = state.lock_model();
     let ctx = model_guard
         .as_mut()
@@ -216,7 +242,7 @@ pub fn transcribe_pcm16(state: &WhisperState, pcm16_24khz: Vec<u8>, language: &s
     }
 
     // Resample from 24kHz to 16kHz (Whisper expects 16kHz)
-    let samples = resample_audio(&samples, INPUT_SAMPLE_RATE, WHISPER_SAMPLE_RATE)?;
+    let samples = decode::resample(&samples, INPUT_SAMPLE_RATE, WHISPER_SAMPLE_RATE)?;
 
     println!(
         "[Whisper] Transcribing {} samples at {}Hz",
@@ -224,10 +250,18 @@ pub fn transcribe_pcm16(state: &WhisperState, pcm16_24khz: Vec<u8>, language: &s
         WHISPER_SAMPLE_RATE
     );
 
-    run_whisper_inference(ctx, &samples, language)
+    run_whisper_inference(ctx, &samples, language, initial_prompt, false).map(|t| t.text)
 }
 
-pub fn transcribe_file_local(state: &WhisperState, file_path: &Path, language: &str) -> Result<String, String> {
+/// Transcribe any supported audio or video file with segment and word
+/// timestamps. Long files are decoded and transcribed in windows.
+pub fn transcribe_file_local(
+    state: &WhisperState,
+    file_path: &Path,
+    language: &str,
+    initial_prompt: Option<&str>,
+    progress: Option<&WindowProgress>,
+) -> Result<TimedTranscript, String> {
     let mut model_guard = state.lock_model();
     let ctx = model_guard
         .as_mut()
@@ -235,102 +269,18 @@ pub fn transcribe_file_local(state: &WhisperState, file_path: &Path, language: &
 
     println!("[Whisper] Transcribing file: {:?}", file_path);
 
-    // Read WAV file and extract f32 samples at 16kHz
-    // The file has already been converted to a suitable format by the transcribe pipeline
-    let samples = read_audio_file_as_f32(file_path)?;
-
-    run_whisper_inference(ctx, &samples, language)
-}
-
-/// Read an audio file (WAV format from ffmpeg pipeline) and return f32 samples at 16kHz mono.
-fn read_audio_file_as_f32(file_path: &Path) -> Result<Vec<f32>, String> {
-    let data = std::fs::read(file_path)
-        .map_err(|e| format!("Failed to read audio file: {}", e))?;
-
-    // Parse WAV header to extract sample rate and format
-    // WAV files from ffmpeg/transcribe pipeline are PCM
-    if data.len() < 44 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
-        return Err("Not a valid WAV file".to_string());
-    }
-
-    // Find "fmt " chunk
-    let mut pos = 12;
-    let mut sample_rate = 16000u32;
-    let mut bits_per_sample = 16u16;
-    let mut num_channels = 1u16;
-
-    while pos + 8 < data.len() {
-        let chunk_id = &data[pos..pos + 4];
-        let chunk_size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
-
-        if chunk_id == b"fmt " && chunk_size >= 16 {
-            num_channels = u16::from_le_bytes([data[pos + 10], data[pos + 11]]);
-            sample_rate = u32::from_le_bytes([data[pos + 12], data[pos + 13], data[pos + 14], data[pos + 15]]);
-            bits_per_sample = u16::from_le_bytes([data[pos + 22], data[pos + 23]]);
-            break;
-        }
-
-        pos += 8 + chunk_size;
-        // Align to 2-byte boundary
-        if chunk_size % 2 != 0 {
-            pos += 1;
-        }
-    }
-
-    // Find "data" chunk
-    pos = 12;
-    let mut audio_data: &[u8] = &[];
-    while pos + 8 < data.len() {
-        let chunk_id = &data[pos..pos + 4];
-        let chunk_size = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
-
-        if chunk_id == b"data" {
-            let data_start = pos + 8;
-            let data_end = (data_start + chunk_size).min(data.len());
-            audio_data = &data[data_start..data_end];
-            break;
-        }
-
-        pos += 8 + chunk_size;
-        if chunk_size % 2 != 0 {
-            pos += 1;
-        }
-    }
-
-    if audio_data.is_empty() {
-        return Err("No audio data found in WAV file".to_string());
-    }
-
-    // Convert to f32 mono samples
-    let mut samples: Vec<f32> = match bits_per_sample {
-        16 => audio_data
-            .chunks_exact(2)
-            .map(|c| i16::from_le_bytes([c[0], c[1]]) as f32 / PCM16_NORMALIZE)
-            .collect(),
-        32 => audio_data
-            .chunks_exact(4)
-            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-            .collect(),
-        _ => return Err(format!("Unsupported bits per sample: {}", bits_per_sample)),
-    };
-
-    // Convert stereo to mono by averaging channels
-    if num_channels == 2 {
-        samples = samples
-            .chunks_exact(2)
-            .map(|pair| (pair[0] + pair[1]) / 2.0)
-            .collect();
-    }
-
-    // Resample to 16kHz if needed
-    if sample_rate != WHISPER_SAMPLE_RATE {
-        samples = resample_audio(&samples, sample_rate, WHISPER_SAMPLE_RATE)?;
-    }
-
-    Ok(samples)
+    chunking::transcribe_windowed(file_path, progress, |samples| {
+        run_whisper_inference(ctx, samples, language, initial_prompt, true)
+    })
 }
 
-fn run_whisper_inference(ctx: &mut WhisperContext, samples: &[f32], language: &str) -> Result<String, String> {
+fn run_whisper_inference(
+    ctx: &mut WhisperContext,
+    samples: &[f32],
+    language: &str,
+    initial_prompt: Option<&str>,
+    word_timestamps: bool,
+) -> Result<TimedTranscript, String> {
     let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
     params.set_print_special(false);
     params.set_print_progress(false);
@@ -360,6 +310,14 @@ fn run_whisper_inference(ctx: &mut WhisperContext, samples: &[f32], language: &s
     // No cross-segment context carryover needed for dictation
     params.set_no_context(true);
 
+    // Dictionary terms bias decoding towards their exact spelling
+    if let Some(prompt) = initial_prompt {
+        params.set_initial_prompt(prompt);
+    }
+
+    // Per-token timing; dictation doesn't need it
+    params.set_token_timestamps(word_timestamps);
+
     // Create a new state for this inference
     let mut state = ctx
         .create_state()
@@ -373,13 +331,77 @@ fn run_whisper_inference(ctx: &mut WhisperContext, samples: &[f32], language: &s
         .map_err(|e| format!("Failed to get segments: {}", e))?;
 
     let mut text = String::new();
+    let mut segments = Vec::new();
+    let mut words = Vec::new();
     for i in 0..num_segments {
         if let Ok(segment_text) = state.full_get_segment_text(i) {
             text.push_str(&segment_text);
+            let t0 = state.full_get_segment_t0(i).unwrap_or(0);
+            let t1 = state.full_get_segment_t1(i).unwrap_or(t0);
+            segments.push(Segment {
+                start: t0 as f64 / TIMESTAMP_UNITS_PER_SEC,
+                end: t1 as f64 / TIMESTAMP_UNITS_PER_SEC,
+                text: segment_text.trim().to_string(),
+            });
+        }
+        if word_timestamps {
+            words.extend(segment_words(ctx, &state, i));
+        }
+    }
+
+    Ok(TimedTranscript {
+        text: text.trim().to_string(),
+        segments,
+        words,
+    })
+}
+
+/// Merge a segment's tokens into words. A token starting with a space begins
+/// a new word; a word is only as certain as its least certain token.
+fn segment_words(ctx: &WhisperContext, state: &whisper_rs::WhisperState, segment: i32) -> Vec<Word> {
+    // Timestamps, language tags and other special tokens sort after end-of-text
+    let eot = ctx.token_eot();
+    let num_tokens = state.full_n_tokens(segment).unwrap_or(0);
+
+    // Tokens can split multi-byte characters, so collect bytes and decode per word
+    let mut words: Vec<(Vec<u8>, Word)> = Vec::new();
+    for j in 0..num_tokens {
+        let (Ok(data), Ok(bytes)) = (
+            state.full_get_token_data(segment, j),
+            state.full_get_token_bytes(segment, j),
+        ) else {
+            continue;
+        };
+        if data.id >= eot || bytes.is_empty() {
+            continue;
+        }
+        let start = data.t0 as f64 / TIMESTAMP_UNITS_PER_SEC;
+        let end = data.t1 as f64 / TIMESTAMP_UNITS_PER_SEC;
+        match words.last_mut() {
+            Some((word_bytes, word)) if bytes[0] != b' ' => {
+                word_bytes.extend_from_slice(&bytes);
+                word.end = end.max(word.end);
+                word.probability = word.probability.map(|p| p.min(data.p));
+            }
+            _ => words.push((
+                bytes,
+                Word {
+                    start,
+                    end,
+                    text: String::new(),
+                    probability: Some(data.p),
+                },
+            )),
         }
     }
 
-    Ok(text.trim().to_string())
+    words
+        .into_iter()
+        .filter_map(|(bytes, word)| {
+            let text = String::from_utf8_lossy(&bytes).trim().to_string();
+            (!text.is_empty()).then_some(Word { text, ..word })
+        })
+        .collect()
 }
 
 pub fn delete_model(model_dir: &Path) -> Result<(), String> {
@@ -390,51 +412,3 @@ pub fn delete_model(model_dir: &Path) -> Result<(), String> {
     }
     Ok(())
 }
-
-/// Resample audio using FFT-based resampling with proper anti-aliasing.
-fn resample_audio(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>, String> {
-    if from_rate == to_rate || samples.is_empty() {
-        return Ok(samples.to_vec());
-    }
-
-    let chunk_size = 1024;
-    let mut resampler = FftFixedIn::<f32>::new(
-        from_rate as usize,
-        to_rate as usize,
-        chunk_size,
-        1, // sub_chunks
-        1, // channels
-    )
-    .map_err(|e| format!("Failed to create resampler: {}", e))?;
-
-    let mut output = Vec::with_capacity(samples.len() * to_rate as usize / from_rate as usize);
-    let mut pos = 0;
-
-    while pos < samples.len() {
-        let frames_needed = resampler.input_frames_next();
-        let end = (pos + frames_needed).min(samples.len());
-        let mut chunk = samples[pos..end].to_vec();
-        chunk.resize(frames_needed, 0.0);
-
-        let resampled = resampler
-            .process(&[chunk], None)
-            .map_err(|e| format!("Resampling failed: {}", e))?;
-
-        if let Some(channel) = resampled.into_iter().next() {
-            output.extend(channel);
-        }
-
-        pos += frames_needed;
-    }
-
-    // Flush remaining samples
-    let frames_needed = resampler.input_frames_next();
-    let flush = vec![0.0f32; frames_needed];
-    if let Ok(resampled) = resampler.process(&[flush], None) {
-        if let Some(channel) = resampled.into_iter().next() {
-            output.extend(channel);
-        }
-    }
-
-    Ok(output)
-}
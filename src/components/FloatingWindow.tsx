@@ -42,10 +42,14 @@ export function FloatingWindow() {
   const [isActive, setIsActive] = useState(false);
   const [isProcessing, setIsProcessing] = useState(false);
   const [processingMessage, setProcessingMessage] = useState("Transcribing...");
+  const [partialText, setPartialText] = useState<string | null>(null);
   const [error, setError] = useState<string | null>(null);
+  const [warning, setWarning] = useState<string | null>(null);
+  const [notice, setNotice] = useState<string | null>(null);
   const [cancelShortcut, setCancelShortcut] = useState("Escape");
   const [recordingShortcut, setRecordingShortcut] =
     useState("Ctrl+Shift+Space");
+  const [holdToTalk, setHoldToTalk] = useState(false);
   const [barHeights, setBarHeights] = useState<number[]>(
     Array(BAR_COUNT).fill(MIN_BAR_HEIGHT)
   );
@@ -216,6 +220,8 @@ export function FloatingWindow() {
         setIsActive(event.payload);
         if (event.payload) {
           setError(null);
+          setWarning(null);
+          setNotice(null);
           setIsProcessing(false);
           const savedCancelShortcut = await store.get<string>(
             STORE_KEYS.CANCEL_SHORTCUT
@@ -223,6 +229,10 @@ export function FloatingWindow() {
           const savedRecordingShortcut = await store.get<string>(
             STORE_KEYS.SHORTCUT
           );
+          const savedShortcutMode = await store.get<string>(
+            STORE_KEYS.SHORTCUT_MODE
+          );
+          setHoldToTalk(savedShortcutMode === "hold");
           if (savedCancelShortcut) {
             setCancelShortcut(savedCancelShortcut);
           }
@@ -247,13 +257,32 @@ export function FloatingWindow() {
       setError(event.payload);
     });
 
+    const unlistenWarning = listen<string>("recording-warning", (event) => {
+      setWarning(event.payload);
+    });
+
+    // A queued dictation was transcribed on retry; it goes to history only
+    const unlistenRecovered = listen<{ id: string; text: string }>(
+      "retry-queue-transcribed",
+      () => {
+        setNotice("Queued dictation transcribed and saved to history");
+      }
+    );
+
     const unlistenProcessing = listen<boolean>("processing-state", (event) => {
       setIsProcessing(event.payload);
+      setPartialText(null);
       if (event.payload) {
         setProcessingMessage("Transcribing...");
       }
     });
 
+    // Streamed LLM output (full text so far) while a mode or rules are applied;
+    // empty when a fallback provider starts over
+    const unlistenPartial = listen<string>("processing-partial", (event) => {
+      setPartialText(event.payload);
+    });
+
     const unlistenProcessingMessage = listen<string>(
       "processing-message",
       (event) => {
@@ -271,7 +300,10 @@ export function FloatingWindow() {
       unlistenExpanded.then((fn) => fn());
       unlistenAudioLevel.then((fn) => fn());
       unlistenError.then((fn) => fn());
+      unlistenWarning.then((fn) => fn());
+      unlistenRecovered.then((fn) => fn());
       unlistenProcessing.then((fn) => fn());
+      unlistenPartial.then((fn) => fn());
       unlistenProcessingMessage.then((fn) => fn());
       unlistenModesUpdated.then((fn) => fn());
     };
@@ -381,12 +413,24 @@ export function FloatingWindow() {
             {isProcessing ? (
               <div className="flex items-center gap-2">
                 <div className="w-4 h-4 border-2 border-violet-400/30 border-t-violet-400 rounded-full animate-spin" />
-                <span className="text-sm text-white/70 font-medium">
-                  {processingMessage}
-                </span>
+                {partialText ? (
+                  // rtl + truncate keeps the newest words visible
+                  <span
+                    className="text-sm text-white/70 font-medium max-w-[280px] truncate"
+                    dir="rtl"
+                  >
+                    <bdi>{partialText}</bdi>
+                  </span>
+                ) : (
+                  <span className="text-sm text-white/70 font-medium">
+                    {processingMessage}
+                  </span>
+                )}
               </div>
             ) : error ? (
               <span className="text-sm text-red-400 font-medium">{error}</span>
+            ) : notice ? (
+              <span className="text-sm text-green-400 font-medium">{notice}</span>
             ) : (
               <div className="flex items-center gap-[2px] h-12">
                 {barHeights.map((height, i) => (
@@ -400,8 +444,12 @@ export function FloatingWindow() {
             )}
           </div>
 
+          {warning && !isProcessing && !error && !notice && (
+            <span className="text-[11px] text-amber-400/80">{warning}</span>
+          )}
+
           {/* Hints row */}
-          {!isProcessing && !error && (
+          {!isProcessing && !error && !notice && (
             <div className="flex items-center gap-4 text-[11px] text-white/40">
               <span className="flex items-center gap-1">
                 <kbd className="px-1.5 py-0.5 bg-white/10 rounded text-white/60 font-mono">
@@ -414,7 +462,7 @@ export function FloatingWindow() {
                 <kbd className="px-1.5 py-0.5 bg-white/10 rounded text-white/60 font-mono">
                   {formatShortcut(recordingShortcut)}
                 </kbd>
-                <span>finish</span>
+                <span>{holdToTalk ? "release to finish" : "finish"}</span>
               </span>
             </div>
           )}
@@ -68,7 +68,7 @@ export function TranscribeSection() {
 
   const {
     history,
-    addToHistory,
+    refresh: refreshHistory,
     removeFromHistory,
     clearHistory,
   } = useTranscriptionHistory();
@@ -76,21 +76,12 @@ export function TranscribeSection() {
   // Ref for scrolling to result
   const resultRef = useRef<HTMLDivElement>(null);
 
-  // Save to history when we get a new result
-  // Note: We intentionally only depend on result and isTranscribing to trigger this effect
-  // once when transcription completes. Other values (inputType, youtubeUrl, etc.) are read
-  // at trigger time and don't need to re-trigger the effect.
+  // The backend records each result in history; reload it when one arrives
   useEffect(() => {
     if (result && !isTranscribing) {
-      const source = inputType === "youtube" ? "youtube" : "file";
-      const sourceName = inputType === "youtube"
-        ? youtubeUrl
-        : (selectedFile?.name || selectedFilePath || "Unknown file");
-
-      addToHistory(source, sourceName, result);
+      refreshHistory();
     }
-    // eslint-disable-next-line react-hooks/exhaustive-deps
-  }, [result, isTranscribing]);
+  }, [result, isTranscribing, refreshHistory]);
 
   // Auto-scroll to result when transcription completes
   useEffect(() => {
@@ -36,7 +36,7 @@ import type { TranscriptionHistoryItem } from "@/hooks/useTranscriptionHistory";
 
 interface TranscriptionHistoryProps {
   history: TranscriptionHistoryItem[];
-  onDelete: (id: string) => void;
+  onDelete: (id: number) => void;
   onClear: () => void;
 }
 
@@ -45,12 +45,12 @@ export function TranscriptionHistory({
   onDelete,
   onClear,
 }: TranscriptionHistoryProps) {
-  const [expandedId, setExpandedId] = useState<string | null>(null);
-  const [deleteItemId, setDeleteItemId] = useState<string | null>(null);
+  const [expandedId, setExpandedId] = useState<number | null>(null);
+  const [deleteItemId, setDeleteItemId] = useState<number | null>(null);
   const [showClearDialog, setShowClearDialog] = useState(false);
-  const [copiedId, setCopiedId] = useState<string | null>(null);
+  const [copiedId, setCopiedId] = useState<number | null>(null);
 
-  const handleCopy = async (text: string, itemId: string) => {
+  const handleCopy = async (text: string, itemId: number) => {
     try {
       await writeText(text);
       setCopiedId(itemId);
@@ -85,12 +85,12 @@ export function TranscriptionHistory({
     }
   };
 
-  const toggleExpanded = (id: string) => {
+  const toggleExpanded = (id: number) => {
     setExpandedId(expandedId === id ? null : id);
   };
 
   const handleDeleteConfirm = () => {
-    if (deleteItemId) {
+    if (deleteItemId !== null) {
       onDelete(deleteItemId);
       setDeleteItemId(null);
       // If we deleted the expanded item, collapse it
@@ -106,7 +106,7 @@ export function TranscriptionHistory({
     setExpandedId(null);
   };
 
-  const itemToDelete = deleteItemId
+  const itemToDelete = deleteItemId !== null
     ? history.find((h) => h.id === deleteItemId)
     : null;
 
@@ -312,7 +312,7 @@ export function TranscriptionHistory({
 
       {/* Delete single item confirmation dialog */}
       <AlertDialog
-        open={!!deleteItemId}
+        open={deleteItemId !== null}
         onOpenChange={(open) => !open && setDeleteItemId(null)}
       >
         <AlertDialogContent>
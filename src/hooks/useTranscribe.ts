@@ -14,11 +14,27 @@ export interface DependencyStatus {
   ffmpeg_version: string | null;
 }
 
+export interface TranscriptSegment {
+  start: number;
+  end: number;
+  text: string;
+}
+
+export interface TranscriptWord {
+  start: number;
+  end: number;
+  text: string;
+  /** 0-1, null when the engine doesn't report confidence */
+  probability: number | null;
+}
+
 export interface TranscriptionResult {
   raw_text: string;
   processed_text: string | null;
   duration_seconds: number;
   word_count: number;
+  segments: TranscriptSegment[];
+  words: TranscriptWord[];
 }
 
 export interface TranscribeProgress {
@@ -1,94 +1,114 @@
-import { useState, useEffect, useCallback, useRef } from "react";
-import { LazyStore } from "@tauri-apps/plugin-store";
-import { STORE_KEYS } from "@/lib/storeKeys";
-import type { TranscriptionResult } from "./useTranscribe";
+import { useState, useEffect, useCallback } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import type {
+  TranscriptionResult,
+  TranscriptSegment,
+  TranscriptWord,
+} from "./useTranscribe";
 
 export interface TranscriptionHistoryItem {
-  id: string;
+  id: number;
   timestamp: number;
   source: "file" | "youtube";
   sourceName: string; // filename or youtube URL
   result: TranscriptionResult;
 }
 
+/** Row of the backend's history database */
+interface HistoryEntry {
+  id: number;
+  created_at: number;
+  source: string;
+  source_name: string | null;
+  raw_text: string;
+  processed_text: string | null;
+  duration_seconds: number;
+  word_count: number;
+  segments: TranscriptSegment[];
+  words: TranscriptWord[];
+}
+
+interface HistoryPage {
+  entries: HistoryEntry[];
+  total: number;
+}
+
 const MAX_HISTORY_ITEMS = 50;
-const store = new LazyStore("settings.json");
+/** Dictations share the database but aren't listed with file transcriptions */
+const SOURCES = ["file", "youtube"];
 
+function toHistoryItem(entry: HistoryEntry): TranscriptionHistoryItem {
+  return {
+    id: entry.id,
+    timestamp: entry.created_at,
+    source: entry.source === "youtube" ? "youtube" : "file",
+    sourceName: entry.source_name ?? "Unknown file",
+    result: {
+      raw_text: entry.raw_text,
+      processed_text: entry.processed_text,
+      duration_seconds: entry.duration_seconds,
+      word_count: entry.word_count,
+      segments: entry.segments,
+      words: entry.words,
+    },
+  };
+}
+
+/**
+ * File and YouTube transcriptions from the backend's history database. The
+ * backend records each result as it finishes; call `refresh` to pick it up.
+ */
 export function useTranscriptionHistory() {
   const [history, setHistory] = useState<TranscriptionHistoryItem[]>([]);
   const [isLoading, setIsLoading] = useState(true);
-  // Track if initial load is complete to avoid saving on mount
-  const isInitialLoad = useRef(true);
 
-  // Load history on mount
-  useEffect(() => {
-    async function loadHistory() {
-      try {
-        const historyJson = await store.get<string>(STORE_KEYS.TRANSCRIPTION_HISTORY);
-        if (historyJson) {
-          const parsed = JSON.parse(historyJson);
-          setHistory(parsed);
-        }
-      } catch (err) {
-        console.error("Failed to load transcription history:", err);
-      } finally {
-        setIsLoading(false);
-        isInitialLoad.current = false;
-      }
+  const refresh = useCallback(async () => {
+    try {
+      const page = await invoke<HistoryPage>("list_history", {
+        offset: 0,
+        limit: MAX_HISTORY_ITEMS,
+        query: null,
+        sources: SOURCES,
+      });
+      setHistory(page.entries.map(toHistoryItem));
+    } catch (err) {
+      console.error("Failed to load transcription history:", err);
+    } finally {
+      setIsLoading(false);
     }
-
-    loadHistory();
   }, []);
 
-  // Persist history whenever it changes (after initial load)
+  // Load history on mount
   useEffect(() => {
-    if (isInitialLoad.current) return;
-
-    const saveHistory = async () => {
-      try {
-        await store.set(STORE_KEYS.TRANSCRIPTION_HISTORY, JSON.stringify(history));
-      } catch (err) {
-        console.error("Failed to save transcription history:", err);
-      }
-    };
-
-    saveHistory();
-  }, [history]);
-
-  const addToHistory = useCallback((
-    source: "file" | "youtube",
-    sourceName: string,
-    result: TranscriptionResult
-  ) => {
-    const newItem: TranscriptionHistoryItem = {
-      id: `transcription-${Date.now()}`,
-      timestamp: Date.now(),
-      source,
-      sourceName,
-      result,
-    };
+    refresh();
+  }, [refresh]);
 
-    setHistory(prev => [newItem, ...prev].slice(0, MAX_HISTORY_ITEMS));
-
-    return newItem;
-  }, []);
-
-  const removeFromHistory = useCallback((id: string) => {
-    setHistory(prev => prev.filter((item) => item.id !== id));
+  const removeFromHistory = useCallback(async (id: number) => {
+    try {
+      await invoke("delete_history_entry", { id });
+      setHistory(prev => prev.filter((item) => item.id !== id));
+    } catch (err) {
+      console.error("Failed to delete transcription:", err);
+    }
   }, []);
 
-  const clearHistory = useCallback(() => {
-    setHistory([]);
+  const clearHistory = useCallback(async () => {
+    try {
+      await invoke("clear_history", { sources: SOURCES });
+      setHistory([]);
+    } catch (err) {
+      console.error("Failed to clear transcription history:", err);
+    }
   }, []);
 
-  const getHistoryItem = useCallback((id: string) => {
+  const getHistoryItem = useCallback((id: number) => {
     return history.find((item) => item.id === id);
   }, [history]);
 
   return {
     history,
     isLoading,
-    addToHistory,
+    refresh,
     removeFromHistory,
     clearHistory,
     getHistoryItem,
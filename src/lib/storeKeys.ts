@@ -32,12 +32,36 @@ export const STORE_KEYS = {
   STATS_TOTAL_WORDS: "statsTotalWords",
   STATS_TOTAL_TRANSCRIPTIONS: "statsTotalTranscriptions",
   STATS_TOTAL_TIME_SAVED_SECONDS: "statsTotalTimeSavedSeconds",
-  TRANSCRIPTION_HISTORY: "transcriptionHistory",
   TRANSCRIBE_LANGUAGE: "transcribeLanguage",
   TRANSCRIBE_APPLY_RULES: "transcribeApplyRules",
   STT_PROVIDER: "sttProvider",
   PURE_PASTE_ENABLED: "purePasteEnabled",
   PURE_PASTE_SHORTCUT: "purePasteShortcut",
+  VAD_ENABLED: "vadEnabled",
+  AUTO_STOP_ENABLED: "autoStopEnabled",
+  AUTO_STOP_SILENCE_SECONDS: "autoStopSilenceSeconds",
+  AUTO_STOP_THRESHOLD: "autoStopThreshold",
+  LONG_RECORDING_ENABLED: "longRecordingEnabled",
+  SHORTCUT_MODE: "shortcutMode",
+  CUSTOM_LLM_BASE_URL: "customLlmBaseUrl",
+  CUSTOM_LLM_API_KEY: "customLlmApiKey",
+  CUSTOM_LLM_MODEL: "customLlmModel",
+  CUSTOM_STT_BASE_URL: "customSttBaseUrl",
+  CUSTOM_STT_API_KEY: "customSttApiKey",
+  CUSTOM_STT_MODEL: "customSttModel",
+  RERUN_SHORTCUT: "rerunShortcut",
+  DICTIONARY: "dictionary",
+  LOCAL_RULES: "localRules",
+  VOICE_COMMANDS_ENABLED: "voiceCommandsEnabled",
+  VOICE_COMMANDS: "voiceCommands",
+  INSERTION_METHOD: "insertionMethod",
+  MODE_INSERTION_METHODS: "modeInsertionMethods",
+  RESTORE_CLIPBOARD: "restoreClipboard",
+  CLIPBOARD_RESTORE_DELAY_MS: "clipboardRestoreDelayMs",
+  APP_PROFILES: "appProfiles",
+  MODE_SHORTCUTS: "modeShortcuts",
+  STT_FALLBACK_CHAIN: "sttFallbackChain",
+  LLM_FALLBACK_CHAIN: "llmFallbackChain",
 } as const;
 
 export type StoreKey = (typeof STORE_KEYS)[keyof typeof STORE_KEYS];